@@ -12,5 +12,5 @@ fn main() {
 
     setup(&root);
 
-    afrish::mainloop();
+    afrish::mainloop().unwrap();
 }
@@ -59,14 +59,17 @@ impl pack::TkPackLayout for TkButton {}
 impl widget::TkLabelOptions for TkButton {}
 
 impl TkButton {
-    /// Sets the function to be called when the button is clicked.
-    pub fn command(&self, command: impl Fn() + Send + 'static) {
-        wish::add_callback0(&self.id, wish::mk_callback0(command));
+    /// Sets the function to be called when the button is clicked,
+    /// returning a handle to unregister it.
+    pub fn command(&self, command: impl Fn() + Send + 'static) -> wish::CallbackHandle {
+        let handle = wish::add_callback(&self.id, &self.id, move |()| command());
         let msg = format!(
-            "{} configure -command {{ puts clicked-{} ; flush stdout }}",
+            "{} configure -command {{ afrish::event clicked-{} }}",
             self.id, self.id
         );
         wish::tell_wish(&msg);
+
+        handle
     }
 
     /// Calls the button's command as if it were clicked.
@@ -0,0 +1,569 @@
+//! Candidate list - a composite widget showing a page of numbered
+//! candidates, such as the suggestions of an input method, one of them
+//! highlighted.
+//!
+//! The list keeps the candidates and the selection itself: the selection
+//! is moved with the select and page methods, or by keys passed to
+//! [handle_key](TkCandidateList::handle_key), and a candidate is committed
+//! by a digit key, Return, or clicking on it:
+//!
+//! ```ignore
+//! let popup = afrish::make_popup_window(&root, afrish::WindowType::PopupMenu);
+//! let list = afrish::make_candidate_list(&popup, afrish::Orientation::Vertical);
+//! list.grid().layout();
+//! list.on_commit(|_index, candidate| println!("chose {}", candidate.text));
+//!
+//! list.set_candidates(&[
+//!     afrish::Candidate::new("ɛ", "3"),
+//!     afrish::Candidate::new("ɔ", "c"),
+//! ]);
+//! popup.show_near(caret_x, caret_top, caret_bottom);
+//! ```
+//!
+//! Each change to the list is sent to wish as one script, so the list can
+//! be refreshed on every keystroke.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use super::font;
+use super::grid;
+use super::pack;
+use super::tcl;
+use super::widget;
+use super::wish;
+
+/// A candidate: its text, and a hint shown beside it, e.g. the rest of
+/// the input that would produce it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Candidate {
+    pub text: String,
+    pub hint: String,
+}
+
+impl Candidate {
+    /// Creates a candidate with given text and hint.
+    pub fn new(text: &str, hint: &str) -> Candidate {
+        Candidate {
+            text: String::from(text),
+            hint: String::from(hint),
+        }
+    }
+}
+
+/// The appearance of a candidate list: colours are given as for
+/// [background](crate::TkTopLevel::background).
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandidateStyle {
+    pub background: String,
+    pub foreground: String,
+    pub hint_foreground: String,
+    pub selected_background: String,
+    pub selected_foreground: String,
+    /// Font of the candidates, or the default font if `None`.
+    pub font: Option<font::TkFont>,
+    /// Font of the hints, or the default font if `None`.
+    pub hint_font: Option<font::TkFont>,
+    /// Horizontal space around each candidate and hint, in pixels.
+    pub padding: u64,
+    /// Whether candidates are shown with the digit committing them.
+    pub numbered: bool,
+}
+
+impl Default for CandidateStyle {
+    fn default() -> CandidateStyle {
+        CandidateStyle {
+            background: String::from("white"),
+            foreground: String::from("black"),
+            hint_foreground: String::from("gray50"),
+            selected_background: String::from("#3584e4"),
+            selected_foreground: String::from("white"),
+            font: None,
+            hint_font: None,
+            padding: 4,
+            numbered: true,
+        }
+    }
+}
+
+type CommitCommand = Arc<Mutex<Box<dyn Fn(usize, Candidate) + Send + 'static>>>;
+
+// The candidates and selection, shared by copies of the list and the
+// commands of its labels.
+struct ListState {
+    candidates: Vec<Candidate>,
+    selected: usize,
+    page_size: usize,
+    orientation: widget::Orientation,
+    style: CandidateStyle,
+    // number of candidate labels created
+    slots: usize,
+    commit: Option<CommitCommand>,
+}
+
+impl ListState {
+    fn page_start(&self) -> usize {
+        self.selected - self.selected % self.page_size
+    }
+}
+
+/// Refers to a candidate list
+#[derive(Clone)]
+pub struct TkCandidateList {
+    pub id: String,
+    state: Arc<Mutex<ListState>>,
+}
+
+impl fmt::Debug for TkCandidateList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TkCandidateList")
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+impl PartialEq for TkCandidateList {
+    fn eq(&self, other: &TkCandidateList) -> bool {
+        self.id == other.id
+    }
+}
+
+/// Creates an empty candidate list in given parent, listing candidates
+/// one above the other or side by side.
+pub fn make_candidate_list(
+    parent: &impl widget::TkWidget,
+    orientation: widget::Orientation,
+) -> TkCandidateList {
+    let id = wish::next_wid(parent.id());
+    let style = CandidateStyle::default();
+    let msg = format!("frame {} -background {}", id, tcl::quote(&style.background));
+    wish::tell_wish(&msg);
+
+    let list = TkCandidateList {
+        id,
+        state: Arc::new(Mutex::new(ListState {
+            candidates: vec![],
+            selected: 0,
+            page_size: 5,
+            orientation,
+            style,
+            slots: 0,
+            commit: None,
+        })),
+    };
+    list.refresh();
+
+    list
+}
+
+impl widget::TkWidget for TkCandidateList {
+    /// Returns the widget's id reference - used within tk
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+impl grid::TkGridLayout for TkCandidateList {}
+impl pack::TkPackLayout for TkCandidateList {}
+
+impl TkCandidateList {
+    /// Returns the candidates in the list.
+    pub fn candidates(&self) -> Vec<Candidate> {
+        self.state.lock().unwrap().candidates.clone()
+    }
+
+    /// Commits the selected candidate, calling the
+    /// [on_commit](TkCandidateList::on_commit) command: returns false if
+    /// the list is empty.
+    pub fn commit(&self) -> bool {
+        let selected = self.state.lock().unwrap().selected;
+        self.commit_index(selected)
+    }
+
+    /// Commits the candidate at given position in the current page,
+    /// counting from 0: returns false if there is no such candidate.
+    pub fn commit_on_page(&self, position: usize) -> bool {
+        let index = {
+            let state = self.state.lock().unwrap();
+            if position >= state.page_size {
+                return false;
+            }
+            state.page_start() + position
+        };
+        self.commit_index(index)
+    }
+
+    // Calls the commit command for candidate at given index, if any.
+    fn commit_index(&self, index: usize) -> bool {
+        let (candidate, commit) = {
+            let state = self.state.lock().unwrap();
+            match state.candidates.get(index) {
+                Some(candidate) => (candidate.clone(), state.commit.clone()),
+                None => return false,
+            }
+        };
+        if let Some(commit) = commit {
+            if let Ok(commit) = commit.try_lock() {
+                commit(index, candidate);
+            }
+        }
+        true
+    }
+
+    /// Acts on given key symbol, as from [TkEvent](widget::TkEvent), returning
+    /// true if the key was used:
+    ///
+    /// * 1 to 9, and 0 for the tenth, commit a candidate on the page
+    /// * Down and Up, or Right and Left when horizontal, select the next or
+    ///   previous candidate
+    /// * Next (page down) and Prior (page up) change page
+    /// * Return commits the selected candidate
+    pub fn handle_key(&self, key_symbol: &str) -> bool {
+        let (vertical, empty) = {
+            let state = self.state.lock().unwrap();
+            (
+                state.orientation == widget::Orientation::Vertical,
+                state.candidates.is_empty(),
+            )
+        };
+        if empty {
+            return false;
+        }
+        match key_symbol {
+            "Down" if vertical => self.select_next(),
+            "Up" if vertical => self.select_prev(),
+            "Right" if !vertical => self.select_next(),
+            "Left" if !vertical => self.select_prev(),
+            "Next" => self.page_next(),
+            "Prior" => self.page_prev(),
+            "Return" | "KP_Enter" => self.commit(),
+            digit => match digit.parse::<usize>() {
+                Ok(0) if digit.len() == 1 => self.commit_on_page(9),
+                Ok(n) if digit.len() == 1 => self.commit_on_page(n - 1),
+                _ => false,
+            },
+        }
+    }
+
+    /// Sends the keys pressed in given widget to [handle_key](TkCandidateList::handle_key),
+    /// stopping the widget's other bindings for keys that are used.
+    pub fn bind_keys(&self, widget: &impl widget::TkWidget) -> widget::BindingHandle {
+        let list = self.clone();
+        widget.bind_filter("<KeyPress>", move |event| {
+            if list.handle_key(&event.key_symbol) {
+                widget::Propagation::Break
+            } else {
+                widget::Propagation::Continue
+            }
+        })
+    }
+
+    /// Sets the command called with the index and candidate committed,
+    /// replacing any previous command.
+    pub fn on_commit(&self, command: impl Fn(usize, Candidate) + Send + 'static) {
+        self.state.lock().unwrap().commit = Some(Arc::new(Mutex::new(Box::new(command))));
+    }
+
+    /// Sets the orientation of the list.
+    pub fn orientation(&self, value: widget::Orientation) {
+        self.state.lock().unwrap().orientation = value;
+        self.refresh();
+    }
+
+    /// Shows the next page, selecting its first candidate: returns false
+    /// if on the last page.
+    pub fn page_next(&self) -> bool {
+        self.update(|state| {
+            let next = state.page_start() + state.page_size;
+            (next < state.candidates.len()).then_some(next)
+        })
+    }
+
+    /// Shows the previous page, selecting its first candidate: returns
+    /// false if on the first page.
+    pub fn page_prev(&self) -> bool {
+        self.update(|state| state.page_start().checked_sub(state.page_size))
+    }
+
+    /// Sets the number of candidates on each page, from 1 to 10: by
+    /// default, 5.
+    pub fn page_size(&self, value: usize) {
+        self.state.lock().unwrap().page_size = value.clamp(1, 10);
+        self.refresh();
+    }
+
+    /// Selects the candidate with given index, changing page if needed:
+    /// returns false if there is no such candidate.
+    pub fn select(&self, index: usize) -> bool {
+        self.update(|state| (index < state.candidates.len()).then_some(index))
+    }
+
+    /// Returns the index of the selected candidate, or `None` if the list
+    /// is empty.
+    pub fn selected(&self) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        (!state.candidates.is_empty()).then_some(state.selected)
+    }
+
+    /// Selects the next candidate, changing page if needed: returns false
+    /// if the last candidate is selected.
+    pub fn select_next(&self) -> bool {
+        self.update(|state| {
+            let next = state.selected + 1;
+            (next < state.candidates.len()).then_some(next)
+        })
+    }
+
+    /// Selects the previous candidate, changing page if needed: returns
+    /// false if the first candidate is selected.
+    pub fn select_prev(&self) -> bool {
+        self.update(|state| state.selected.checked_sub(1))
+    }
+
+    /// Replaces the candidates in the list, selecting the first.
+    pub fn set_candidates(&self, candidates: &[Candidate]) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.candidates = candidates.to_vec();
+            state.selected = 0;
+        }
+        self.refresh();
+    }
+
+    /// Sets the appearance of the list.
+    pub fn style(&self, value: CandidateStyle) {
+        self.state.lock().unwrap().style = value;
+        self.refresh();
+    }
+
+    /// Returns the appearance of the list.
+    pub fn style_get(&self) -> CandidateStyle {
+        self.state.lock().unwrap().style.clone()
+    }
+
+    // Selects the candidate given by function, if any, and shows it.
+    fn update(&self, select: impl FnOnce(&ListState) -> Option<usize>) -> bool {
+        {
+            let mut state = self.state.lock().unwrap();
+            match select(&state) {
+                Some(index) => state.selected = index,
+                None => return false,
+            }
+        }
+        self.refresh();
+        true
+    }
+
+    // Sends one script showing the current page, creating labels as
+    // needed.
+    fn refresh(&self) {
+        let mut state = self.state.lock().unwrap();
+        let mut script = vec![];
+
+        // -- create labels, with commands committing their candidate
+        for slot in state.slots..state.page_size {
+            let text = format!("{}.c{}", self.id, slot);
+            let hint = format!("{}.h{}", self.id, slot);
+            let list = self.clone();
+            wish::add_callback(&self.id, &text, move |()| {
+                list.commit_on_page(slot);
+            });
+            for label in [&text, &hint] {
+                script.push(format!(
+                    "label {} -anchor w -borderwidth 0; bind {} <ButtonPress-1> {{afrish::event clicked-{}}}",
+                    label, label, text
+                ));
+            }
+        }
+        state.slots = state.slots.max(state.page_size);
+
+        let style = &state.style;
+        let font = |font: &Option<font::TkFont>| match font {
+            Some(font) => tcl::quote(&font.to_string()),
+            None => String::from("TkDefaultFont"),
+        };
+        script.push(format!(
+            "{} configure -background {}",
+            self.id,
+            tcl::quote(&style.background)
+        ));
+
+        let page_start = state.page_start();
+        for slot in 0..state.slots {
+            let text = format!("{}.c{}", self.id, slot);
+            let hint = format!("{}.h{}", self.id, slot);
+            let candidate = match state.candidates.get(page_start + slot) {
+                Some(candidate) if slot < state.page_size => candidate,
+                _ => {
+                    script.push(format!("grid remove {} {}", text, hint));
+                    continue;
+                }
+            };
+            let (background, foreground, hint_foreground) = if page_start + slot == state.selected {
+                (
+                    &style.selected_background,
+                    &style.selected_foreground,
+                    &style.selected_foreground,
+                )
+            } else {
+                (&style.background, &style.foreground, &style.hint_foreground)
+            };
+            let label = if style.numbered {
+                format!("{}. {}", (slot + 1) % 10, candidate.text)
+            } else {
+                candidate.text.clone()
+            };
+            let (row, column) = match state.orientation {
+                widget::Orientation::Vertical => (slot, 0),
+                widget::Orientation::Horizontal => (0, 2 * slot),
+            };
+
+            script.push(format!(
+                "{} configure -text {} -font {} -background {} -foreground {}",
+                text,
+                tcl::quote(&label),
+                font(&style.font),
+                tcl::quote(background),
+                tcl::quote(foreground)
+            ));
+            script.push(format!(
+                "{} configure -text {} -font {} -background {} -foreground {}",
+                hint,
+                tcl::quote(&candidate.hint),
+                font(&style.hint_font),
+                tcl::quote(background),
+                tcl::quote(hint_foreground)
+            ));
+            script.push(format!(
+                "grid {} -row {} -column {} -sticky nsew -ipadx {}",
+                text, row, column, style.padding
+            ));
+            script.push(format!(
+                "grid {} -row {} -column {} -sticky nsew -ipadx {}",
+                hint,
+                row,
+                column + 1,
+                style.padding
+            ));
+        }
+
+        wish::tell_wish(&script.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+
+    fn candidates(count: usize) -> Vec<Candidate> {
+        (0..count)
+            .map(|n| Candidate::new(&format!("c{}", n), &format!("h{}", n)))
+            .collect()
+    }
+
+    #[test]
+    fn candidate_list_1() {
+        // -- selection moves across pages
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let list = make_candidate_list(&session.root(), widget::Orientation::Vertical);
+        assert_eq!(None, list.selected());
+        list.page_size(3);
+        list.set_candidates(&candidates(7));
+        assert_eq!(Some(0), list.selected());
+
+        assert!(list.select_next());
+        assert!(list.select_next());
+        assert!(list.select_next());
+        assert_eq!(Some(3), list.selected());
+        assert!(list.select_prev());
+        assert_eq!(Some(2), list.selected());
+        assert!(list.page_next());
+        assert_eq!(Some(3), list.selected());
+        assert!(list.page_next());
+        assert_eq!(Some(6), list.selected());
+        assert!(!list.page_next());
+        assert!(!list.select_next());
+        assert!(list.page_prev());
+        assert_eq!(Some(3), list.selected());
+        assert!(list.select(0));
+        assert!(!list.select_prev());
+        assert!(!list.page_prev());
+        assert!(!list.select(7));
+    }
+
+    #[test]
+    fn candidate_list_2() {
+        // -- candidates are committed by key or click
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let list = make_candidate_list(&session.root(), widget::Orientation::Horizontal);
+        let committed = Arc::new(Mutex::new(vec![]));
+        let committed_clone = committed.clone();
+        list.on_commit(move |index, candidate| {
+            committed_clone
+                .lock()
+                .unwrap()
+                .push((index, candidate.text));
+        });
+        list.set_candidates(&candidates(7));
+
+        assert!(list.handle_key("Right"));
+        assert!(!list.handle_key("Down"));
+        assert!(list.handle_key("Return"));
+        assert!(list.handle_key("Next"));
+        assert!(list.handle_key("2"));
+        assert!(!list.handle_key("3"));
+        assert!(!list.handle_key("a"));
+        mock.event(&format!("clicked-{}.c0", list.id));
+        session.process_pending().unwrap();
+        assert_eq!(
+            vec![
+                (1, String::from("c1")),
+                (6, String::from("c6")),
+                (5, String::from("c5"))
+            ],
+            *committed.lock().unwrap()
+        );
+
+        list.set_candidates(&[]);
+        assert!(!list.handle_key("Return"));
+    }
+
+    #[test]
+    fn candidate_list_3() {
+        // -- each update is one script
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let list = make_candidate_list(&session.root(), widget::Orientation::Vertical);
+        list.page_size(2);
+        mock.clear();
+        list.set_candidates(&[Candidate::new("ɛ b", "3")]);
+        let id = &list.id;
+        assert_eq!(
+            vec![[
+                format!("{} configure -background {{white}}", id),
+                format!(
+                    "{}.c0 configure -text {{1. ɛ b}} -font TkDefaultFont -background {{#3584e4}} -foreground {{white}}",
+                    id
+                ),
+                format!(
+                    "{}.h0 configure -text {{3}} -font TkDefaultFont -background {{#3584e4}} -foreground {{white}}",
+                    id
+                ),
+                format!("grid {}.c0 -row 0 -column 0 -sticky nsew -ipadx 4", id),
+                format!("grid {}.h0 -row 0 -column 1 -sticky nsew -ipadx 4", id),
+                format!("grid remove {}.c1 {}.h1", id, id),
+                // -- labels beyond the page size are kept, hidden
+                format!("grid remove {}.c2 {}.h2", id, id),
+                format!("grid remove {}.c3 {}.h3", id, id),
+                format!("grid remove {}.c4 {}.h4", id, id),
+            ]
+            .join("\n")],
+            mock.commands()
+        );
+    }
+}
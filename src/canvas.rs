@@ -2,9 +2,11 @@
 //!
 //! * also see the Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/canvas.htm)
 
+use super::event;
 use super::grid;
 use super::image;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -107,19 +109,37 @@ impl TkCanvas {
     /// Configures the item(s) with given tag.
     pub fn configure_tag(&self, tag: &str, option: &str, value: &str) {
         let msg = format!(
-            "{} itemconfigure {} -{} {{{}}}",
-            &self.id, tag, option, value
+            "{} itemconfigure {} -{} {}",
+            &self.id,
+            tcl::quote(tag),
+            option,
+            tcl::quote(value)
         );
         wish::tell_wish(&msg);
     }
 
+    /// Configures the item(s) with given tag, returning the error
+    /// reported by Tk if the option or value is invalid.
+    pub fn try_configure_tag(
+        &self,
+        tag: &str,
+        option: &str,
+        value: &str,
+    ) -> Result<(), wish::TkError> {
+        let msg = format!(
+            "{} itemconfigure {} -{} {}",
+            &self.id,
+            tcl::quote(tag),
+            option,
+            tcl::quote(value)
+        );
+        wish::try_tell_wish(&msg)
+    }
+
     /// Creates an arc where (x1, y1) (x2, y2) define a rectangle
     /// enclosing the oval which defines the arc.
     pub fn create_arc(&self, (x1, y1): (u64, u64), (x2, y2): (u64, u64)) -> TkCanvasArc {
-        let msg = format!(
-            "puts [{} create arc {} {} {} {}] ; flush stdout",
-            &self.id, x1, y1, x2, y2
-        );
+        let msg = format!("{} create arc {} {} {} {}", &self.id, x1, y1, x2, y2);
         let id = wish::ask_wish(&msg);
 
         TkCanvasArc {
@@ -130,10 +150,7 @@ impl TkCanvas {
 
     /// Creates an image at (x, y) according to given image reference.
     pub fn create_image(&self, (x, y): (u64, u64), image: &image::TkImage) -> TkCanvasImage {
-        let msg = format!(
-            "puts [{} create image {} {} {}] ; flush stdout",
-            &self.id, x, y, &image.id
-        );
+        let msg = format!("{} create image {} {} {}", &self.id, x, y, &image.id);
         let id = wish::ask_wish(&msg);
 
         TkCanvasImage {
@@ -149,10 +166,7 @@ impl TkCanvas {
             line_defn.push_str(&format!("{} {} ", x, y));
         }
 
-        let msg = format!(
-            "puts [{} create line {}] ; flush stdout",
-            &self.id, &line_defn
-        );
+        let msg = format!("{} create line {}", &self.id, &line_defn);
         let id = wish::ask_wish(&msg);
 
         TkCanvasLine {
@@ -164,10 +178,7 @@ impl TkCanvas {
     /// Creates an oval where (x1, y1) (x2, y2) define a rectangle
     /// enclosing the oval.
     pub fn create_oval(&self, (x1, y1): (u64, u64), (x2, y2): (u64, u64)) -> TkCanvasOval {
-        let msg = format!(
-            "puts [{} create oval {} {} {} {}] ; flush stdout",
-            &self.id, x1, y1, x2, y2
-        );
+        let msg = format!("{} create oval {} {} {} {}", &self.id, x1, y1, x2, y2);
         let id = wish::ask_wish(&msg);
 
         TkCanvasOval {
@@ -183,10 +194,7 @@ impl TkCanvas {
             line_defn.push_str(&format!("{} {} ", x, y));
         }
 
-        let msg = format!(
-            "puts [{} create polygon {}] ; flush stdout",
-            &self.id, &line_defn
-        );
+        let msg = format!("{} create polygon {}", &self.id, &line_defn);
         let id = wish::ask_wish(&msg);
 
         TkCanvasPolygon {
@@ -201,10 +209,7 @@ impl TkCanvas {
         (x1, y1): (u64, u64),
         (x2, y2): (u64, u64),
     ) -> TkCanvasRectangle {
-        let msg = format!(
-            "puts [{} create rectangle {} {} {} {}] ; flush stdout",
-            &self.id, x1, y1, x2, y2
-        );
+        let msg = format!("{} create rectangle {} {} {} {}", &self.id, x1, y1, x2, y2);
         let id = wish::ask_wish(&msg);
 
         TkCanvasRectangle {
@@ -215,10 +220,7 @@ impl TkCanvas {
 
     /// Creates a text item at (x, y) with given contents.
     pub fn create_text(&self, (x, y): (u64, u64), text: &str) -> TkCanvasText {
-        let msg = format!(
-            "puts [{} create text {} {} {{{}}}] ; flush stdout",
-            &self.id, x, y, text
-        );
+        let msg = format!("{} create text {} {} {}", &self.id, x, y, tcl::quote(text));
         let id = wish::ask_wish(&msg);
 
         TkCanvasText {
@@ -233,13 +235,7 @@ impl TkCanvas {
         (x, y): (u64, u64),
         widget: &impl widget::TkWidget,
     ) -> TkCanvasWidget {
-        let msg = format!(
-            "puts [{} create window {} {} {}] ; flush stdout",
-            &self.id,
-            x,
-            y,
-            widget.id()
-        );
+        let msg = format!("{} create window {} {} {}", &self.id, x, y, widget.id());
         let id = wish::ask_wish(&msg);
 
         TkCanvasWidget {
@@ -282,31 +278,51 @@ pub trait TkCanvasItem {
     fn canvas(&self) -> &str;
     fn id(&self) -> &str;
 
-    /// Binds event to item.
-    fn bind(&self, pattern: &str, command: impl Fn(widget::TkEvent) + Send + 'static) {
-        // tag+pattern used as identifier, as multiple commands can be bound to each entity
-        let tag_pattern = format!("{}{}{}", self.canvas(), self.id(), pattern);
-        wish::add_callback1_event(&tag_pattern, wish::mk_callback1_event(command));
+    /// Binds event to item, in addition to any commands already bound to
+    /// it.
+    ///
+    /// # Panics
+    ///
+    /// If the pattern cannot be parsed.
+    fn bind(
+        &self,
+        pattern: impl Into<event::EventPattern>,
+        command: impl Fn(widget::TkEvent) + Send + 'static,
+    ) -> widget::BindingHandle {
+        let bind_command = format!("{} bind {}", self.canvas(), self.id());
+        let name = format!("{}{}", self.canvas(), self.id());
+        widget::bind_event(
+            &bind_command,
+            self.canvas(),
+            &name,
+            &pattern.into(),
+            command,
+        )
+    }
+
+    /// Configures the individual item.
+    fn configure(&self, option: &str, value: &str) {
         let msg = format!(
-            "{} bind {} {} {{ puts cb1e:{}:%x:%y:%X:%Y:%h:%w:%k:%K:%b ; flush stdout }}",
+            "{} itemconfigure {} -{} {}",
             self.canvas(),
             self.id(),
-            pattern,
-            tag_pattern
+            option,
+            tcl::quote(value)
         );
         wish::tell_wish(&msg);
     }
 
-    /// Configures the individual item.
-    fn configure(&self, option: &str, value: &str) {
+    /// Configures the individual item, returning the error reported
+    /// by Tk if the option or value is invalid.
+    fn try_configure(&self, option: &str, value: &str) -> Result<(), wish::TkError> {
         let msg = format!(
-            "{} itemconfigure {} -{} {{{}}}",
+            "{} itemconfigure {} -{} {}",
             self.canvas(),
             self.id(),
             option,
-            value
+            tcl::quote(value)
         );
-        wish::tell_wish(&msg);
+        wish::try_tell_wish(&msg)
     }
 }
 
@@ -315,13 +331,18 @@ pub trait TkCanvasItem {
 pub trait TkCanvasTags: TkCanvasItem {
     /// Adds given tag to this canvas item.
     fn add_tag(&self, tag: &str) {
-        let msg = format!("{} addtag {} withtag {}", &self.canvas(), tag, &self.id());
+        let msg = format!(
+            "{} addtag {} withtag {}",
+            &self.canvas(),
+            tcl::quote(tag),
+            &self.id()
+        );
         wish::tell_wish(&msg);
     }
 
     /// Deletes tag from this canvas item.
     fn delete_tag(&self, tag: &str) {
-        let msg = format!("{} dtag {} {}", &self.canvas(), &self.id(), tag);
+        let msg = format!("{} dtag {} {}", &self.canvas(), &self.id(), tcl::quote(tag));
         wish::tell_wish(&msg);
     }
 
@@ -329,13 +350,7 @@ pub trait TkCanvasTags: TkCanvasItem {
     fn get_tags(&self) -> Vec<String> {
         let msg = format!("{} gettags {}", &self.canvas(), &self.id());
         let tags = wish::ask_wish(&msg);
-
-        let mut result: Vec<String> = vec![];
-        for tag in tags.split_whitespace() {
-            result.push(String::from(tag));
-        }
-
-        result
+        tcl::list_items(&tags)
     }
 }
 
@@ -505,7 +520,7 @@ impl TkCanvasLine {
     /// Shape of arrow(s) to draw: see Tk
     /// [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/canvas.htm#M145).
     pub fn arrow_shape(&self, v1: u64, v2: u64, v3: u64) {
-        let msg = format!("{{{} {} {} }}", v1, v2, v3);
+        let msg = format!("{} {} {}", v1, v2, v3);
         self.configure("arrowshape", &msg);
     }
 
@@ -735,3 +750,96 @@ impl TkCanvasWidget {
         self.configure("anchor", &value.to_string());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn create_line() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let canvas = make_canvas(&session.root());
+        assert_eq!(Some(format!("canvas {}", canvas.id)), mock.last_command());
+
+        mock.reply(&format!("{} create line 1 2 30 40 ", canvas.id), "7");
+        let line = canvas.create_line(&[(1, 2), (30, 40)]);
+        assert_eq!("7", line.id);
+
+        line.dash(TkDash::DashDot);
+        line.arrow(TkArrowWhere::Last);
+        line.width(3);
+        assert_eq!(
+            vec![
+                format!("{} itemconfigure 7 -dash {{-.}}", canvas.id),
+                format!("{} itemconfigure 7 -arrow {{last}}", canvas.id),
+                format!("{} itemconfigure 7 -width {{3}}", canvas.id),
+            ],
+            mock.commands()[2..]
+        );
+    }
+
+    #[test]
+    fn items_and_tags() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let canvas = make_canvas(&session.root());
+
+        mock.reply(&format!("{} create rectangle 0 0 10 20", canvas.id), "1");
+        mock.reply(&format!("{} create text 5 5 {{a label}}", canvas.id), "2");
+        let rectangle = canvas.create_rectangle((0, 0), (10, 20));
+        let text = canvas.create_text((5, 5), "a label");
+        assert_eq!(("1", "2"), (rectangle.id.as_str(), text.id.as_str()));
+
+        mock.clear();
+        rectangle.add_tag("box");
+        canvas.configure_tag("box", "fill", "red");
+        canvas.delete(&text);
+        assert_eq!(
+            vec![
+                format!("{} addtag {{box}} withtag 1", canvas.id),
+                format!("{} itemconfigure {{box}} -fill {{red}}", canvas.id),
+                format!("{} delete 2", canvas.id),
+            ],
+            mock.commands()
+        );
+
+        mock.reply(&format!("{} gettags 1", canvas.id), "box current");
+        assert_eq!(vec!["box", "current"], rectangle.get_tags());
+    }
+
+    #[test]
+    fn item_bind() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let canvas = make_canvas(&session.root());
+        mock.reply(&format!("{} create oval 0 0 5 5", canvas.id), "3");
+        let oval = canvas.create_oval((0, 0), (5, 5));
+
+        let clicked = Arc::new(Mutex::new(None));
+        let clicked_at = clicked.clone();
+        let binding = oval.bind("<Button-1>", move |event| {
+            *clicked_at.lock().unwrap() = Some((event.x, event.y));
+        });
+        assert!(binding
+            .key()
+            .starts_with(&format!("{}3<ButtonPress-1>#", canvas.id)));
+        assert_eq!(
+            Some(format!(
+                "{} bind 3 {{<ButtonPress-1>}} {{+afrish::bound {{{}}} %x %y %X %Y %h %w %k %K %b %A %s %D %W %t %N %d %T}}",
+                canvas.id,
+                binding.key()
+            )),
+            mock.last_command()
+        );
+
+        mock.event(&format!("cb1e {{{}}} 4 2 0 0 0 0 0 ?? 1", binding.key()));
+        session.process_pending().unwrap();
+        assert_eq!(Some((4, 2)), *clicked.lock().unwrap());
+    }
+}
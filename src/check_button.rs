@@ -71,14 +71,16 @@ impl widget::TkLabelOptions for TkCheckButton {}
 impl TkCheckButton {
     /// Sets the function to be called when the button is clicked.
     /// This function takes one boolean parameter, which is the _new_ state
-    /// of the check button.
-    pub fn command(&self, command: impl Fn(bool) + Send + 'static) {
-        wish::add_callback1_bool(&self.id, wish::mk_callback1_bool(command));
+    /// of the check button. Returns a handle to unregister the function.
+    pub fn command(&self, command: impl Fn(bool) + Send + 'static) -> wish::CallbackHandle {
+        let handle = wish::add_callback(&self.id, &self.id, command);
         let msg = format!(
-            "{} configure -command {{ puts cb1b-{}-${} ; flush stdout }}",
+            "{} configure -command {{ afrish::event cb1b-{}-${} }}",
             self.id, self.id, self.var
         );
         wish::tell_wish(&msg);
+
+        handle
     }
 
     /// Toggles the button's state and calls the button's command,
@@ -90,7 +92,7 @@ impl TkCheckButton {
 
     /// Returns true/false if button is selected (checked) or not.
     pub fn is_selected(&self) -> bool {
-        let msg = format!("puts ${} ; flush stdout", self.var);
+        let msg = format!("set {}", self.var);
         let result = wish::ask_wish(&msg);
         result == "1"
     }
@@ -9,6 +9,7 @@
 
 use super::grid;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -22,15 +23,7 @@ pub struct TkCombobox {
 /// populating the drop-down list with the given set of values.
 pub fn make_combobox(parent: &impl widget::TkWidget, values: &[&str]) -> TkCombobox {
     let id = wish::next_wid(parent.id());
-    let mut values_str = String::new();
-    for value in values {
-        values_str.push('{');
-        values_str.push_str(value);
-        values_str.push('}');
-        values_str.push(' ');
-    }
-
-    let msg = format!("ttk::combobox {} -values {{{}}}", id, values_str);
+    let msg = format!("ttk::combobox {} -values {}", id, tcl::quote_list(values));
     wish::tell_wish(&msg);
 
     TkCombobox { id }
@@ -48,7 +41,7 @@ impl pack::TkPackLayout for TkCombobox {}
 impl TkCombobox {
     /// Sets the height of the widget, in rows
     pub fn height(&self, value: u64) {
-        let msg = format!("{} configure -height {{{}}}", self.id, value);
+        let msg = format!("{} configure -height {}", self.id, value);
         wish::tell_wish(&msg);
     }
 
@@ -64,19 +57,19 @@ impl TkCombobox {
 
     /// Sets the current value
     pub fn value(&self, value: &str) {
-        let msg = format!("puts [{} set {{{}}}] ; flush stdout", self.id, value);
+        let msg = format!("{} set {}", self.id, tcl::quote(value));
         wish::tell_wish(&msg);
     }
 
     /// Returns the current value
     pub fn value_get(&self) -> String {
-        let msg = format!("puts [{} get] ; flush stdout", self.id);
+        let msg = format!("{} get", self.id);
         wish::ask_wish(&msg)
     }
 
     /// Sets the width of the widget, in characters
     pub fn width(&self, value: u64) {
-        let msg = format!("{} configure -width {{{}}}", self.id, value);
+        let msg = format!("{} configure -width {}", self.id, value);
         wish::tell_wish(&msg);
     }
 }
@@ -41,6 +41,7 @@
 //!
 
 use super::font;
+use super::tcl;
 use super::toplevel;
 use super::widget;
 use super::wish;
@@ -118,20 +119,33 @@ impl TkMessageBox {
     /// Returns a string for the name of the button pressed.
     ///
     pub fn show(&self) -> String {
-        let mut msg = String::from("puts [tk_messageBox ");
+        wish::ask_wish(&self.script())
+    }
+
+    /// Shows the message box as [show](Self::show), but returns a future,
+    /// so an async program is not blocked while waiting for the user.
+    /// Available with the "async" feature.
+    #[cfg(feature = "async")]
+    pub async fn show_async(&self) -> String {
+        wish::ask_wish_async(&self.script()).await
+    }
+
+    // Returns the Tcl command showing the message box.
+    fn script(&self) -> String {
+        let mut msg = String::from("tk_messageBox ");
 
         if let Some(default) = &self.default {
-            msg.push_str(&format!("-default {{{}}} ", default));
+            msg.push_str(&format!("-default {} ", tcl::quote(default)));
         }
 
         if let Some(detail) = &self.detail {
-            msg.push_str(&format!("-detail {{{}}} ", detail));
+            msg.push_str(&format!("-detail {} ", tcl::quote(detail)));
         }
 
         msg.push_str(&format!("-icon {} ", self.icon));
 
         if let Some(message) = &self.message {
-            msg.push_str(&format!("-message {{{}}} ", message));
+            msg.push_str(&format!("-message {} ", tcl::quote(message)));
         }
 
         if let Some(parent) = &self.parent {
@@ -139,13 +153,12 @@ impl TkMessageBox {
         }
 
         if let Some(title) = &self.title {
-            msg.push_str(&format!("-title {{{}}} ", title));
+            msg.push_str(&format!("-title {} ", tcl::quote(title)));
         }
 
         msg.push_str(&format!("-type {} ", self.type_buttons));
-        msg.push_str("] ; flush stdout");
 
-        wish::ask_wish(&msg)
+        msg
     }
 }
 
@@ -198,28 +211,34 @@ impl TkColourChooser {
     /// * `None` - if cancel pressed.
     ///
     pub fn show(&self) -> Option<String> {
-        let mut msg = String::from("puts [tk_chooseColor ");
+        chosen(wish::ask_wish(&self.script()))
+    }
+
+    /// Shows the colour chooser as [show](Self::show), but returns a future,
+    /// so an async program is not blocked while waiting for the user.
+    /// Available with the "async" feature.
+    #[cfg(feature = "async")]
+    pub async fn show_async(&self) -> Option<String> {
+        chosen(wish::ask_wish_async(&self.script()).await)
+    }
+
+    // Returns the Tcl command showing the colour chooser.
+    fn script(&self) -> String {
+        let mut msg = String::from("tk_chooseColor ");
 
         if let Some(parent) = &self.parent {
             msg.push_str(&format!("-parent {} ", parent));
         }
 
         if let Some(title) = &self.title {
-            msg.push_str(&format!("-title {{{}}} ", title));
+            msg.push_str(&format!("-title {} ", tcl::quote(title)));
         }
 
         if let Some(initial) = &self.initial {
-            msg.push_str(&format!("-initialcolor {{{}}} ", initial));
+            msg.push_str(&format!("-initialcolor {} ", tcl::quote(initial)));
         }
 
-        msg.push_str("] ; flush stdout");
-
-        let result = wish::ask_wish(&msg);
-        if result.is_empty() {
-            None
-        } else {
-            Some(result)
-        }
+        msg
     }
 }
 
@@ -275,18 +294,31 @@ impl TkDirectoryChooser {
     /// * `None` - if cancel pressed.
     ///
     pub fn show(&self) -> Option<String> {
-        let mut msg = String::from("puts [tk_chooseDirectory ");
+        chosen(wish::ask_wish(&self.script()))
+    }
+
+    /// Shows the directory chooser as [show](Self::show), but returns a future,
+    /// so an async program is not blocked while waiting for the user.
+    /// Available with the "async" feature.
+    #[cfg(feature = "async")]
+    pub async fn show_async(&self) -> Option<String> {
+        chosen(wish::ask_wish_async(&self.script()).await)
+    }
+
+    // Returns the Tcl command showing the directory chooser.
+    fn script(&self) -> String {
+        let mut msg = String::from("tk_chooseDirectory ");
 
         if let Some(parent) = &self.parent {
             msg.push_str(&format!("-parent {} ", parent));
         }
 
         if let Some(title) = &self.title {
-            msg.push_str(&format!("-title {{{}}} ", title));
+            msg.push_str(&format!("-title {} ", tcl::quote(title)));
         }
 
         if let Some(initial) = &self.initial {
-            msg.push_str(&format!("-initialdir {{{}}} ", initial));
+            msg.push_str(&format!("-initialdir {} ", tcl::quote(initial)));
         }
 
         if self.must_exist {
@@ -294,14 +326,7 @@ impl TkDirectoryChooser {
             msg.push_str("-mustexist 1 ");
         }
 
-        msg.push_str("] ; flush stdout");
-
-        let result = wish::ask_wish(&msg);
-        if result.is_empty() {
-            None
-        } else {
-            Some(result)
-        }
+        msg
     }
 }
 
@@ -377,44 +402,44 @@ impl TkOpenFileChooser {
     /// * `None` - if cancel pressed.
     ///
     pub fn show(&self) -> Option<String> {
-        let mut msg = String::from("puts [tk_getOpenFile ");
+        chosen(wish::ask_wish(&self.script()))
+    }
+
+    /// Shows the file chooser as [show](Self::show), but returns a future,
+    /// so an async program is not blocked while waiting for the user.
+    /// Available with the "async" feature.
+    #[cfg(feature = "async")]
+    pub async fn show_async(&self) -> Option<String> {
+        chosen(wish::ask_wish_async(&self.script()).await)
+    }
+
+    // Returns the Tcl command showing the file chooser.
+    fn script(&self) -> String {
+        let mut msg = String::from("tk_getOpenFile ");
 
         if let Some(parent) = &self.parent {
             msg.push_str(&format!("-parent {} ", parent));
         }
 
         if let Some(title) = &self.title {
-            msg.push_str(&format!("-title {{{}}} ", title));
+            msg.push_str(&format!("-title {} ", tcl::quote(title)));
         }
 
         if let Some(types) = &self.file_types {
             if !types.is_empty() {
-                msg.push_str("-filetypes {");
-
-                for (txt, pat) in types {
-                    msg.push_str(&format!("{{{{{}}} {{{}}}}} ", *txt, *pat));
-                }
-
-                msg.push_str("} ");
+                msg.push_str(&format!("-filetypes {} ", file_types_list(types)));
             }
         }
 
         if let Some(initial) = &self.initial_directory {
-            msg.push_str(&format!("-initialdir {{{}}} ", initial));
+            msg.push_str(&format!("-initialdir {} ", tcl::quote(initial)));
         }
 
         if let Some(initial) = &self.initial_filename {
-            msg.push_str(&format!("-initialfile {{{}}} ", initial));
+            msg.push_str(&format!("-initialfile {} ", tcl::quote(initial)));
         }
 
-        msg.push_str("] ; flush stdout");
-
-        let result = wish::ask_wish(&msg);
-        if result.is_empty() {
-            None
-        } else {
-            Some(result)
-        }
+        msg
     }
 }
 
@@ -500,14 +525,27 @@ impl TkSaveFileChooser {
     /// * `None` - if cancel pressed.
     ///
     pub fn show(&self) -> Option<String> {
-        let mut msg = String::from("puts [tk_getSaveFile ");
+        chosen(wish::ask_wish(&self.script()))
+    }
+
+    /// Shows the file chooser as [show](Self::show), but returns a future,
+    /// so an async program is not blocked while waiting for the user.
+    /// Available with the "async" feature.
+    #[cfg(feature = "async")]
+    pub async fn show_async(&self) -> Option<String> {
+        chosen(wish::ask_wish_async(&self.script()).await)
+    }
+
+    // Returns the Tcl command showing the file chooser.
+    fn script(&self) -> String {
+        let mut msg = String::from("tk_getSaveFile ");
 
         if let Some(parent) = &self.parent {
             msg.push_str(&format!("-parent {} ", parent));
         }
 
         if let Some(title) = &self.title {
-            msg.push_str(&format!("-title {{{}}} ", title));
+            msg.push_str(&format!("-title {} ", tcl::quote(title)));
         }
 
         msg.push_str(&format!(
@@ -517,32 +555,38 @@ impl TkSaveFileChooser {
 
         if let Some(types) = &self.file_types {
             if !types.is_empty() {
-                msg.push_str("-filetypes {");
-
-                for (txt, pat) in types {
-                    msg.push_str(&format!("{{{{{}}} {{{}}}}} ", *txt, *pat));
-                }
-
-                msg.push_str("} ");
+                msg.push_str(&format!("-filetypes {} ", file_types_list(types)));
             }
         }
 
         if let Some(initial) = &self.initial_directory {
-            msg.push_str(&format!("-initialdir {{{}}} ", initial));
+            msg.push_str(&format!("-initialdir {} ", tcl::quote(initial)));
         }
 
         if let Some(initial) = &self.initial_filename {
-            msg.push_str(&format!("-initialfile {{{}}} ", initial));
+            msg.push_str(&format!("-initialfile {} ", tcl::quote(initial)));
         }
 
-        msg.push_str("] ; flush stdout");
+        msg
+    }
+}
 
-        let result = wish::ask_wish(&msg);
-        if result.is_empty() {
-            None
-        } else {
-            Some(result)
-        }
+// Returns the -filetypes list, each type being a list of description
+// and pattern(s).
+fn file_types_list(types: &[(String, String)]) -> String {
+    let types: Vec<String> = types
+        .iter()
+        .map(|(text, pattern)| tcl::join_list(&[text, pattern]))
+        .collect();
+    tcl::quote_list(&types)
+}
+
+// Returns the chosen value, or None if the dialog was cancelled.
+fn chosen(result: String) -> Option<String> {
+    if result.is_empty() {
+        None
+    } else {
+        Some(result)
     }
 }
 
@@ -556,15 +600,19 @@ pub fn font_chooser_parent(parent: &impl widget::TkWidget) {
 
 /// Set the title for the font-chooser.
 pub fn font_chooser_title(title: &str) {
-    let msg = format!("tk fontchooser configure -title {}", title);
+    let msg = format!("tk fontchooser configure -title {}", tcl::quote(title));
     wish::tell_wish(&msg);
 }
 
 /// Set the command to be called when a font is chosen.
-pub fn font_chooser_command(command: impl Fn(font::TkFont) + Send + 'static) {
-    wish::add_callback1_font("font", wish::mk_callback1_font(command));
+pub fn font_chooser_command(
+    command: impl Fn(font::TkFont) + Send + 'static,
+) -> wish::CallbackHandle {
+    let handle = wish::add_callback("", "font", command);
     let msg = "tk fontchooser configure -command [list font_choice font]";
     wish::tell_wish(msg);
+
+    handle
 }
 
 /// Get the font for the font-chooser.
@@ -575,7 +623,7 @@ pub fn font_chooser_font_get() -> String {
 
 /// Set the font for the font-chooser.
 pub fn font_chooser_font_set(font: &str) {
-    let msg = format!("tk fontchooser configure -font {}", font);
+    let msg = format!("tk fontchooser configure -font {}", tcl::quote(font));
     wish::tell_wish(&msg);
 }
 
@@ -597,3 +645,72 @@ pub fn font_chooser_visible() -> bool {
     let result = wish::ask_wish(msg);
     result == "1"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+
+    #[test]
+    fn message_box_show() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+
+        let command = "tk_messageBox -detail {Unsaved changes will be lost.} -icon question \
+                       -message {Quit?} -title {Confirm} -type yesno ";
+        mock.reply(command, "yes");
+        let result = message_box()
+            .title("Confirm")
+            .message("Quit?")
+            .detail("Unsaved changes will be lost.")
+            .icon(widget::IconImage::Question)
+            .type_buttons(widget::DialogType::YesNo)
+            .show();
+        assert_eq!("yes", result);
+        assert_eq!(vec![command], mock.commands());
+    }
+
+    #[test]
+    fn open_file_chooser_show() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+
+        let command = "tk_getOpenFile -title {Open} -filetypes {{{Text files} .txt}} \
+                       -initialdir {/tmp} ";
+        mock.reply(command, "/tmp/notes.txt");
+        let mut chooser = open_file_chooser();
+        chooser
+            .title("Open")
+            .file_types(&[("Text files", ".txt")])
+            .initial_directory("/tmp");
+        assert_eq!(Some(String::from("/tmp/notes.txt")), chooser.show());
+
+        // -- cancelling returns an empty string
+        mock.reply(command, "");
+        assert_eq!(None, chooser.show());
+    }
+
+    #[test]
+    fn colour_and_directory_choosers() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+
+        colour_chooser()
+            .title("Colour")
+            .initial_colour("#ff0000")
+            .show();
+        directory_chooser().must_exist(true).show();
+        save_file_chooser().confirm_overwrite(false).show();
+        assert_eq!(
+            vec![
+                "tk_chooseColor -title {Colour} -initialcolor {#ff0000} ",
+                "tk_chooseDirectory -mustexist 1 ",
+                "tk_getSaveFile -confirmoverwrite 0 ",
+            ],
+            mock.commands()
+        );
+    }
+}
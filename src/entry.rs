@@ -68,13 +68,13 @@ impl TkEntry {
 
     /// Returns the current entry value
     pub fn value_get(&self) -> String {
-        let msg = format!("puts ${} ; flush stdout", self.var);
+        let msg = format!("set {}", self.var);
         wish::ask_wish(&msg)
     }
 
     /// Sets the width of the widget, in characters
     pub fn width(&self, value: u64) {
-        let msg = format!("{} configure -width {{{}}}", self.id, value);
+        let msg = format!("{} configure -width {}", self.id, value);
         wish::tell_wish(&msg);
     }
 }
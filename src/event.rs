@@ -0,0 +1,1036 @@
+//! Event patterns - the events which commands are bound to.
+//!
+//! An [EventPattern] is made with its builder methods, or parsed from Tk's
+//! syntax, and checked as it is made:
+//!
+//! ```
+//! use afrish::{EventKind, EventPattern};
+//!
+//! let pattern = EventPattern::button(1).control().double();
+//! assert_eq!("<Control-Double-ButtonPress-1>", pattern.to_string());
+//!
+//! let pattern: EventPattern = "<B1-Motion>".parse().unwrap();
+//! assert_eq!(EventPattern::new(EventKind::Motion).modifier(afrish::Modifier::Button1), pattern);
+//!
+//! assert!("<Contrl-Button-1>".parse::<EventPattern>().is_err());
+//! ```
+//!
+//! The `bind` methods accept an `EventPattern`, or a string which is
+//! parsed as one. A string which cannot be parsed is never sent to wish:
+//! `bind` panics, and [try_bind](crate::widget::TkWidget::try_bind)
+//! returns an error.
+//!
+//! ## Virtual events
+//!
+//! Virtual events, such as `<<CandidateChosen>>`, are bound like any other
+//! event. They can be triggered by key sequences, using
+//! [add_virtual_event], or generated on a widget with a string of data,
+//! which is passed to bound commands in [TkEvent::data](crate::widget::TkEvent::data):
+//!
+//! ```ignore
+//! list.bind("<<CandidateChosen>>", |event| println!("chose {}", event.data));
+//! list.generate_event("<<CandidateChosen>>").data("ɛ").generate().unwrap();
+//!
+//! afrish::add_virtual_event("<<Commit>>", ["<Return>", "<space>"]);
+//! ```
+//!
+//! * also see the Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/bind.htm)
+//!   and [event](https://www.tcl-lang.org/man/tcl8.6/TkCmd/event.htm) pages
+
+use std::fmt;
+use std::str;
+
+use super::tcl;
+use super::widget;
+use super::wish;
+
+/// The types of physical event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Activate,
+    ButtonPress,
+    ButtonRelease,
+    Circulate,
+    CirculateRequest,
+    Colormap,
+    Configure,
+    ConfigureRequest,
+    Create,
+    Deactivate,
+    Destroy,
+    Enter,
+    Expose,
+    FocusIn,
+    FocusOut,
+    Gravity,
+    KeyPress,
+    KeyRelease,
+    Leave,
+    Map,
+    MapRequest,
+    Motion,
+    MouseWheel,
+    Property,
+    Reparent,
+    ResizeRequest,
+    Unmap,
+    Visibility,
+}
+
+const EVENT_KINDS: [EventKind; 28] = [
+    EventKind::Activate,
+    EventKind::ButtonPress,
+    EventKind::ButtonRelease,
+    EventKind::Circulate,
+    EventKind::CirculateRequest,
+    EventKind::Colormap,
+    EventKind::Configure,
+    EventKind::ConfigureRequest,
+    EventKind::Create,
+    EventKind::Deactivate,
+    EventKind::Destroy,
+    EventKind::Enter,
+    EventKind::Expose,
+    EventKind::FocusIn,
+    EventKind::FocusOut,
+    EventKind::Gravity,
+    EventKind::KeyPress,
+    EventKind::KeyRelease,
+    EventKind::Leave,
+    EventKind::Map,
+    EventKind::MapRequest,
+    EventKind::Motion,
+    EventKind::MouseWheel,
+    EventKind::Property,
+    EventKind::Reparent,
+    EventKind::ResizeRequest,
+    EventKind::Unmap,
+    EventKind::Visibility,
+];
+
+impl EventKind {
+    // True for events whose detail is a key symbol.
+    fn is_key(&self) -> bool {
+        matches!(self, EventKind::KeyPress | EventKind::KeyRelease)
+    }
+
+    // True for events whose detail is a button number.
+    fn is_button(&self) -> bool {
+        matches!(self, EventKind::ButtonPress | EventKind::ButtonRelease)
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl str::FromStr for EventKind {
+    type Err = ParseEventPatternErr;
+
+    /// Parses the name of an event type, including the short forms "Key"
+    /// and "Button".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Key" => Ok(EventKind::KeyPress),
+            "Button" => Ok(EventKind::ButtonPress),
+            _ => EVENT_KINDS
+                .iter()
+                .find(|kind| kind.to_string() == s)
+                .copied()
+                .ok_or(ParseEventPatternErr),
+        }
+    }
+}
+
+/// The modifiers which must be held for an event to match a pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Control,
+    Alt,
+    Shift,
+    Lock,
+    Extended,
+    /// Mouse buttons 1 to 5 held down, e.g. for `<Button1-Motion>`.
+    Button1,
+    Button2,
+    Button3,
+    Button4,
+    Button5,
+    Mod1,
+    Mod2,
+    Mod3,
+    Mod4,
+    Mod5,
+    Meta,
+    /// The Command key, on macOS.
+    Command,
+    /// The Option key, on macOS.
+    Option,
+    /// Matches whatever modifiers are held.
+    Any,
+}
+
+const MODIFIERS: [Modifier; 19] = [
+    Modifier::Control,
+    Modifier::Alt,
+    Modifier::Shift,
+    Modifier::Lock,
+    Modifier::Extended,
+    Modifier::Button1,
+    Modifier::Button2,
+    Modifier::Button3,
+    Modifier::Button4,
+    Modifier::Button5,
+    Modifier::Mod1,
+    Modifier::Mod2,
+    Modifier::Mod3,
+    Modifier::Mod4,
+    Modifier::Mod5,
+    Modifier::Meta,
+    Modifier::Command,
+    Modifier::Option,
+    Modifier::Any,
+];
+
+impl fmt::Display for Modifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl str::FromStr for Modifier {
+    type Err = ParseEventPatternErr;
+
+    /// Parses the name of a modifier, including the short forms such as
+    /// "B1", "M1" and "M".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "B1" => Ok(Modifier::Button1),
+            "B2" => Ok(Modifier::Button2),
+            "B3" => Ok(Modifier::Button3),
+            "B4" => Ok(Modifier::Button4),
+            "B5" => Ok(Modifier::Button5),
+            "M1" => Ok(Modifier::Mod1),
+            "M2" => Ok(Modifier::Mod2),
+            "M3" => Ok(Modifier::Mod3),
+            "M4" => Ok(Modifier::Mod4),
+            "M5" => Ok(Modifier::Mod5),
+            "M" => Ok(Modifier::Meta),
+            _ => MODIFIERS
+                .iter()
+                .find(|modifier| modifier.to_string() == s)
+                .copied()
+                .ok_or(ParseEventPatternErr),
+        }
+    }
+}
+
+/// A pattern of events to bind a command to: a physical event, such as a
+/// key press, or a virtual event, such as `<<Paste>>`. Patterns may be
+/// followed by others, to match a sequence of events.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EventPattern {
+    events: Vec<PatternEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum PatternEvent {
+    Physical {
+        modifiers: Vec<Modifier>,
+        repeat: u8,
+        kind: EventKind,
+        detail: Option<String>,
+    },
+    Virtual(String),
+    // Text which could not be parsed, rejected before reaching wish.
+    Raw(String),
+}
+
+impl EventPattern {
+    /// Matches events of given kind.
+    pub fn new(kind: EventKind) -> EventPattern {
+        EventPattern {
+            events: vec![PatternEvent::Physical {
+                modifiers: vec![],
+                repeat: 1,
+                kind,
+                detail: None,
+            }],
+        }
+    }
+
+    /// Matches a press of the key with given symbol, e.g. "a", "Return" or
+    /// "F1".
+    ///
+    /// # Panics
+    ///
+    /// If the key symbol is not a single character or a name made of
+    /// letters, digits and underscores.
+    pub fn key(keysym: &str) -> EventPattern {
+        assert!(is_keysym(keysym), "invalid key symbol {:?}", keysym);
+        EventPattern::new(EventKind::KeyPress).with_detail(keysym)
+    }
+
+    /// Matches a press of given mouse button.
+    ///
+    /// # Panics
+    ///
+    /// If the button is not from 1 to 5.
+    pub fn button(button: u8) -> EventPattern {
+        assert!((1..=5).contains(&button), "invalid mouse button {}", button);
+        EventPattern::new(EventKind::ButtonPress).with_detail(&button.to_string())
+    }
+
+    /// Matches the virtual event with given name, e.g. "Paste" for
+    /// `<<Paste>>`.
+    ///
+    /// # Panics
+    ///
+    /// If the name is empty or contains angle brackets.
+    pub fn virtual_event(name: &str) -> EventPattern {
+        assert!(is_virtual_name(name), "invalid virtual event {:?}", name);
+        EventPattern {
+            events: vec![PatternEvent::Virtual(String::from(name))],
+        }
+    }
+
+    /// Sets the key symbol or button number for the last event, e.g. for
+    /// a key release.
+    ///
+    /// # Panics
+    ///
+    /// If the last event is not a key or button event, or the detail is
+    /// not valid for it.
+    pub fn detail(self, detail: &str) -> EventPattern {
+        match self.events.last() {
+            Some(PatternEvent::Physical { kind, .. }) if kind.is_key() => {
+                assert!(is_keysym(detail), "invalid key symbol {:?}", detail)
+            }
+            Some(PatternEvent::Physical { kind, .. }) if kind.is_button() => {
+                assert!(is_button(detail), "invalid mouse button {:?}", detail)
+            }
+            _ => panic!("only key and button events have a detail"),
+        }
+        self.with_detail(detail)
+    }
+
+    /// Adds a modifier to the last event.
+    ///
+    /// # Panics
+    ///
+    /// If the last event is a virtual event, or unparsed text.
+    pub fn modifier(mut self, modifier: Modifier) -> EventPattern {
+        match self.events.last_mut() {
+            Some(PatternEvent::Physical { modifiers, .. }) => {
+                if !modifiers.contains(&modifier) {
+                    modifiers.push(modifier);
+                }
+            }
+            _ => panic!("only physical events have modifiers"),
+        }
+        self
+    }
+
+    /// Adds the Control modifier to the last event.
+    pub fn control(self) -> EventPattern {
+        self.modifier(Modifier::Control)
+    }
+
+    /// Adds the Alt modifier to the last event.
+    pub fn alt(self) -> EventPattern {
+        self.modifier(Modifier::Alt)
+    }
+
+    /// Adds the Shift modifier to the last event.
+    pub fn shift(self) -> EventPattern {
+        self.modifier(Modifier::Shift)
+    }
+
+    /// Matches the last event occurring twice in quick succession.
+    pub fn double(self) -> EventPattern {
+        self.repeat(2)
+    }
+
+    /// Matches the last event occurring three times in quick succession.
+    pub fn triple(self) -> EventPattern {
+        self.repeat(3)
+    }
+
+    /// Matches the last event occurring four times in quick succession.
+    pub fn quadruple(self) -> EventPattern {
+        self.repeat(4)
+    }
+
+    /// Matches this pattern followed by the other.
+    pub fn then(mut self, other: EventPattern) -> EventPattern {
+        self.events.extend(other.events);
+        self
+    }
+
+    /// Returns true if this is a virtual event.
+    pub fn is_virtual(&self) -> bool {
+        matches!(self.events[..], [PatternEvent::Virtual(_)])
+    }
+
+    // Returns an error if any part of the pattern could not be parsed.
+    pub(super) fn check(&self) -> Result<(), wish::TkError> {
+        if self
+            .events
+            .iter()
+            .any(|event| matches!(event, PatternEvent::Raw(_)))
+        {
+            return Err(wish::TkError::InvalidPattern {
+                pattern: self.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn with_detail(mut self, value: &str) -> EventPattern {
+        if let Some(PatternEvent::Physical { detail, .. }) = self.events.last_mut() {
+            *detail = Some(String::from(value));
+        }
+        self
+    }
+
+    fn repeat(mut self, count: u8) -> EventPattern {
+        match self.events.last_mut() {
+            Some(PatternEvent::Physical { repeat, .. }) => *repeat = count,
+            _ => panic!("only physical events can be repeated"),
+        }
+        self
+    }
+}
+
+// True for names such as "Return" or "KP_Enter", or a single character.
+fn is_keysym(text: &str) -> bool {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => !c.is_whitespace() && !c.is_control() && !"<>-".contains(c),
+        (Some(_), Some(_)) => text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+fn is_button(text: &str) -> bool {
+    matches!(text, "1" | "2" | "3" | "4" | "5")
+}
+
+fn is_virtual_name(text: &str) -> bool {
+    !text.is_empty() && !text.contains(['<', '>'])
+}
+
+impl fmt::Display for EventPattern {
+    /// Writes the pattern in Tk's syntax, e.g. `<Control-KeyPress-a>`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in &self.events {
+            match event {
+                PatternEvent::Virtual(name) => write!(f, "<<{}>>", name)?,
+                PatternEvent::Raw(text) => write!(f, "{}", text)?,
+                PatternEvent::Physical {
+                    modifiers,
+                    repeat,
+                    kind,
+                    detail,
+                } => {
+                    write!(f, "<")?;
+                    for modifier in modifiers {
+                        write!(f, "{}-", modifier)?;
+                    }
+                    match repeat {
+                        2 => write!(f, "Double-")?,
+                        3 => write!(f, "Triple-")?,
+                        4 => write!(f, "Quadruple-")?,
+                        _ => {}
+                    }
+                    write!(f, "{}", kind)?;
+                    if let Some(detail) = detail {
+                        write!(f, "-{}", detail)?;
+                    }
+                    write!(f, ">")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error returned if an event pattern is incorrectly formatted, or names
+/// an unknown event, modifier or button.
+#[derive(Debug, PartialEq)]
+pub struct ParseEventPatternErr;
+
+impl fmt::Display for ParseEventPatternErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid event pattern")
+    }
+}
+
+impl str::FromStr for EventPattern {
+    type Err = ParseEventPatternErr;
+
+    /// Parses a pattern in Tk's syntax, e.g. `<Control-Button-1>`,
+    /// `<Double-1>`, `<<Paste>>` or `a`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut events = vec![];
+        let mut rest = s.trim();
+        while let Some(c) = rest.chars().next() {
+            if let Some(virtual_event) = rest.strip_prefix("<<") {
+                let (name, tail) = virtual_event.split_once(">>").ok_or(ParseEventPatternErr)?;
+                if !is_virtual_name(name) {
+                    return Err(ParseEventPatternErr);
+                }
+                events.push(PatternEvent::Virtual(String::from(name)));
+                rest = tail;
+            } else if let Some(physical) = rest.strip_prefix('<') {
+                let (fields, tail) = physical.split_once('>').ok_or(ParseEventPatternErr)?;
+                events.push(parse_physical(fields)?);
+                rest = tail;
+            } else if c.is_whitespace() {
+                rest = rest.trim_start();
+            } else {
+                // -- a single character is a key press
+                if !is_keysym(&c.to_string()) {
+                    return Err(ParseEventPatternErr);
+                }
+                events.push(PatternEvent::Physical {
+                    modifiers: vec![],
+                    repeat: 1,
+                    kind: EventKind::KeyPress,
+                    detail: Some(c.to_string()),
+                });
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+
+        if events.is_empty() {
+            Err(ParseEventPatternErr)
+        } else {
+            Ok(EventPattern { events })
+        }
+    }
+}
+
+// Parses the fields of a physical event, e.g. "Control-Button-1": its
+// modifiers, then its kind and detail, either of which may be left out.
+fn parse_physical(fields: &str) -> Result<PatternEvent, ParseEventPatternErr> {
+    let fields: Vec<&str> = fields
+        .split(|c: char| c == '-' || c.is_whitespace())
+        .collect();
+    let mut modifiers = vec![];
+    let mut repeat = 1;
+    let mut i = 0;
+    while let Some(field) = fields.get(i) {
+        match *field {
+            "Double" => repeat = 2,
+            "Triple" => repeat = 3,
+            "Quadruple" => repeat = 4,
+            _ => match field.parse::<Modifier>() {
+                Ok(modifier) if i + 1 < fields.len() => modifiers.push(modifier),
+                _ => break,
+            },
+        }
+        i += 1;
+    }
+
+    let (kind, detail) = match &fields[i..] {
+        [kind, detail] => (kind.parse::<EventKind>()?, Some(*detail)),
+        [field] => match field.parse::<EventKind>() {
+            Ok(kind) => (kind, None),
+            // -- a detail alone is a button number or key symbol
+            Err(_) if is_button(field) => (EventKind::ButtonPress, Some(*field)),
+            Err(_) => (EventKind::KeyPress, Some(*field)),
+        },
+        _ => return Err(ParseEventPatternErr),
+    };
+    if let Some(detail) = detail {
+        let valid = (kind.is_key() && is_keysym(detail)) || (kind.is_button() && is_button(detail));
+        if !valid {
+            return Err(ParseEventPatternErr);
+        }
+    }
+
+    Ok(PatternEvent::Physical {
+        modifiers,
+        repeat,
+        kind,
+        detail: detail.map(String::from),
+    })
+}
+
+impl From<EventKind> for EventPattern {
+    fn from(kind: EventKind) -> EventPattern {
+        EventPattern::new(kind)
+    }
+}
+
+impl From<&str> for EventPattern {
+    /// Parses the pattern: a pattern which cannot be parsed is kept as
+    /// text, and rejected when bound. Use `parse` to check a pattern
+    /// first.
+    fn from(pattern: &str) -> EventPattern {
+        pattern.parse().unwrap_or_else(|_| EventPattern {
+            events: vec![PatternEvent::Raw(String::from(pattern))],
+        })
+    }
+}
+
+impl From<&EventPattern> for EventPattern {
+    fn from(pattern: &EventPattern) -> EventPattern {
+        pattern.clone()
+    }
+}
+
+// -- virtual events
+
+/// Makes the virtual event occur whenever any of the given sequences
+/// occur, in addition to any sequences already added.
+///
+/// # Panics
+///
+/// If the first pattern is not a virtual event, or a sequence cannot be
+/// parsed.
+pub fn add_virtual_event<P: Into<EventPattern>>(
+    virtual_event: impl Into<EventPattern>,
+    sequences: impl IntoIterator<Item = P>,
+) {
+    wish::tell_wish(&virtual_event_command("add", virtual_event, sequences));
+}
+
+/// Stops the virtual event occurring for given sequences: with no
+/// sequences, the virtual event is removed.
+///
+/// # Panics
+///
+/// If the first pattern is not a virtual event, or a sequence cannot be
+/// parsed.
+pub fn delete_virtual_event<P: Into<EventPattern>>(
+    virtual_event: impl Into<EventPattern>,
+    sequences: impl IntoIterator<Item = P>,
+) {
+    wish::tell_wish(&virtual_event_command("delete", virtual_event, sequences));
+}
+
+/// Returns the virtual events which have been added.
+pub fn virtual_events() -> Vec<EventPattern> {
+    parse_patterns(&wish::ask_wish("event info"))
+}
+
+/// Returns the sequences making the virtual event occur.
+///
+/// # Panics
+///
+/// If the pattern is not a virtual event.
+pub fn virtual_event_sequences(virtual_event: impl Into<EventPattern>) -> Vec<EventPattern> {
+    let virtual_event = virtual_event.into();
+    assert!(
+        virtual_event.is_virtual(),
+        "{} is not a virtual event",
+        virtual_event
+    );
+    let msg = format!("event info {}", tcl::quote(&virtual_event.to_string()));
+    parse_patterns(&wish::ask_wish(&msg))
+}
+
+fn virtual_event_command<P: Into<EventPattern>>(
+    action: &str,
+    virtual_event: impl Into<EventPattern>,
+    sequences: impl IntoIterator<Item = P>,
+) -> String {
+    let virtual_event = virtual_event.into();
+    assert!(
+        virtual_event.is_virtual(),
+        "{} is not a virtual event",
+        virtual_event
+    );
+    let mut msg = format!(
+        "event {} {}",
+        action,
+        tcl::quote(&virtual_event.to_string())
+    );
+    for sequence in sequences {
+        let sequence = sequence.into();
+        if let Err(error) = sequence.check() {
+            panic!("{}", error);
+        }
+        msg.push(' ');
+        msg.push_str(&tcl::quote(&sequence.to_string()));
+    }
+    msg
+}
+
+// Parses a list of patterns returned by wish, skipping any not understood.
+fn parse_patterns(text: &str) -> Vec<EventPattern> {
+    tcl::list_items(text)
+        .iter()
+        .filter_map(|pattern| pattern.parse().ok())
+        .collect()
+}
+
+// -- generating events
+
+/// When a generated event is processed, relative to other events.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum When {
+    /// Processed immediately, before generating returns.
+    #[default]
+    Now,
+    /// Queued behind any events already waiting.
+    Tail,
+    /// Queued in front of any events already waiting.
+    Head,
+    /// Queued in front of waiting events, but behind other events queued
+    /// with `Mark`.
+    Mark,
+}
+
+impl fmt::Display for When {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            When::Now => "now",
+            When::Tail => "tail",
+            When::Head => "head",
+            When::Mark => "mark",
+        };
+        write!(f, "{}", &value)
+    }
+}
+
+/// Refers to an event being generated on a widget: created by
+/// [generate_event](widget::TkWidget::generate_event).
+#[derive(Clone, Debug)]
+pub struct TkEventGenerator {
+    widget: String,
+    pattern: EventPattern,
+    button: Option<u8>,
+    data: Option<String>,
+    delta: Option<i64>,
+    keysym: Option<String>,
+    modifiers: Option<widget::Modifiers>,
+    root_x: Option<i64>,
+    root_y: Option<i64>,
+    warp: bool,
+    when: When,
+    x: Option<i64>,
+    y: Option<i64>,
+}
+
+impl TkEventGenerator {
+    pub(super) fn new(widget: &str, pattern: EventPattern) -> TkEventGenerator {
+        TkEventGenerator {
+            widget: String::from(widget),
+            pattern,
+            button: None,
+            data: None,
+            delta: None,
+            keysym: None,
+            modifiers: None,
+            root_x: None,
+            root_y: None,
+            warp: false,
+            when: When::Now,
+            x: None,
+            y: None,
+        }
+    }
+
+    /// Sets the mouse button, for button events.
+    pub fn button(&mut self, button: u8) -> &mut Self {
+        self.button = Some(button);
+        self
+    }
+
+    /// Sets the data passed with a virtual event.
+    pub fn data(&mut self, data: &str) -> &mut Self {
+        self.data = Some(String::from(data));
+        self
+    }
+
+    /// Sets the distance the mouse wheel turned, for MouseWheel events.
+    pub fn delta(&mut self, delta: i64) -> &mut Self {
+        self.delta = Some(delta);
+        self
+    }
+
+    /// Sets the key symbol, e.g. "a" or "Return", for key events.
+    pub fn keysym(&mut self, keysym: &str) -> &mut Self {
+        self.keysym = Some(String::from(keysym));
+        self
+    }
+
+    /// Sets the modifiers held during the event.
+    pub fn modifiers(&mut self, modifiers: widget::Modifiers) -> &mut Self {
+        self.modifiers = Some(modifiers);
+        self
+    }
+
+    /// Sets the position of the mouse relative to the screen.
+    pub fn root_position(&mut self, x: i64, y: i64) -> &mut Self {
+        self.root_x = Some(x);
+        self.root_y = Some(y);
+        self
+    }
+
+    /// Moves the mouse pointer to the event's position, for Motion events.
+    pub fn warp(&mut self, value: bool) -> &mut Self {
+        self.warp = value;
+        self
+    }
+
+    /// Sets when the event is processed: by default, immediately.
+    pub fn when(&mut self, value: When) -> &mut Self {
+        self.when = value;
+        self
+    }
+
+    /// Sets the position of the mouse relative to the widget.
+    pub fn position(&mut self, x: i64, y: i64) -> &mut Self {
+        self.x = Some(x);
+        self.y = Some(y);
+        self
+    }
+
+    fn option_string(&self) -> String {
+        let mut msg = String::new();
+
+        if let Some(button) = self.button {
+            msg.push_str(&format!(" -button {}", button));
+        }
+        if let Some(data) = &self.data {
+            msg.push_str(&format!(" -data {}", tcl::quote(data)));
+        }
+        if let Some(delta) = self.delta {
+            msg.push_str(&format!(" -delta {}", delta));
+        }
+        if let Some(keysym) = &self.keysym {
+            msg.push_str(&format!(" -keysym {}", tcl::quote(keysym)));
+        }
+        if let Some(modifiers) = self.modifiers {
+            msg.push_str(&format!(" -state {}", modifiers.bits()));
+        }
+        if let (Some(x), Some(y)) = (self.root_x, self.root_y) {
+            msg.push_str(&format!(" -rootx {} -rooty {}", x, y));
+        }
+        if self.warp {
+            msg.push_str(" -warp 1");
+        }
+        if self.when != When::Now {
+            msg.push_str(&format!(" -when {}", self.when));
+        }
+        if let (Some(x), Some(y)) = (self.x, self.y) {
+            msg.push_str(&format!(" -x {} -y {}", x, y));
+        }
+
+        msg
+    }
+
+    /// Generates the event, returning an error if the pattern cannot be
+    /// parsed, or if wish rejects it, e.g. because an option does not apply
+    /// to the event.
+    ///
+    /// Bindings run by an event processed now cannot stop it with a
+    /// [Propagation](widget::Propagation): queue the event with
+    /// [when](TkEventGenerator::when) for them to do so.
+    pub fn generate(&self) -> Result<(), wish::TkError> {
+        self.pattern.check()?;
+        let msg = format!(
+            "event generate {} {}{}",
+            &self.widget,
+            tcl::quote(&self.pattern.to_string()),
+            &self.option_string()
+        );
+        wish::try_tell_wish(&msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+    use crate::widget::TkWidget;
+
+    fn canonical(pattern: &str) -> Result<String, ParseEventPatternErr> {
+        pattern
+            .parse::<EventPattern>()
+            .map(|pattern| pattern.to_string())
+    }
+
+    #[test]
+    fn parse_pattern_1() {
+        assert_eq!(Ok(String::from("<ButtonPress-3>")), canonical("<3>"));
+        assert_eq!(Ok(String::from("<KeyPress-Return>")), canonical("<Return>"));
+        assert_eq!(
+            Ok(String::from("<Double-ButtonPress-1>")),
+            canonical("<Double-1>")
+        );
+        assert_eq!(
+            Ok(String::from("<Button3-Motion>")),
+            canonical("<B3-Motion>")
+        );
+        assert_eq!(
+            Ok(String::from("<Button1-ButtonRelease>")),
+            canonical("<B1-ButtonRelease>")
+        );
+        assert_eq!(
+            Ok(String::from("<Control-Shift-KeyPress-a>")),
+            canonical("<Control-Shift-Key-a>")
+        );
+        assert_eq!(
+            Ok(String::from("<<ListboxSelect>>")),
+            canonical("<<ListboxSelect>>")
+        );
+        assert_eq!(
+            Ok(String::from("<KeyPress-Escape><KeyPress-x>")),
+            canonical("<Escape>x")
+        );
+        assert_eq!(
+            Ok(String::from("<KeyPress-Control_L>")),
+            canonical("<Control_L>")
+        );
+    }
+
+    #[test]
+    fn parse_pattern_2() {
+        assert_eq!(Err(ParseEventPatternErr), canonical("<Contrl-Button-1>"));
+        assert_eq!(Err(ParseEventPatternErr), canonical("<Button-7>"));
+        assert_eq!(Err(ParseEventPatternErr), canonical("<Enter-a>"));
+        assert_eq!(Err(ParseEventPatternErr), canonical("<Control->"));
+        assert_eq!(Err(ParseEventPatternErr), canonical("<Button-1"));
+        assert_eq!(Err(ParseEventPatternErr), canonical("<<>>"));
+        assert_eq!(Err(ParseEventPatternErr), canonical(""));
+
+        // -- strings which cannot be parsed are kept as they are, for Tk
+        let pattern = EventPattern::from("<Contrl-Button-1>");
+        assert_eq!("<Contrl-Button-1>", pattern.to_string());
+        assert!(!pattern.is_virtual());
+    }
+
+    #[test]
+    fn build_pattern() {
+        assert_eq!(
+            "<Control-Alt-KeyPress-s>",
+            EventPattern::key("s").control().alt().to_string()
+        );
+        assert_eq!(
+            "<KeyRelease-Shift_L>",
+            EventPattern::new(EventKind::KeyRelease)
+                .detail("Shift_L")
+                .to_string()
+        );
+        assert_eq!(
+            "<Triple-ButtonPress-1><<Paste>>",
+            EventPattern::button(1)
+                .triple()
+                .then(EventPattern::virtual_event("Paste"))
+                .to_string()
+        );
+        assert!(EventPattern::virtual_event("Paste").is_virtual());
+    }
+
+    #[test]
+    fn virtual_events_1() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+
+        add_virtual_event("<<Commit>>", ["<Return>", "<Control-j>"]);
+        assert_eq!(
+            Some(String::from(
+                "event add {<<Commit>>} {<KeyPress-Return>} {<Control-KeyPress-j>}"
+            )),
+            mock.last_command()
+        );
+        delete_virtual_event("<<Commit>>", [EventPattern::key("Return")]);
+        assert_eq!(
+            Some(String::from(
+                "event delete {<<Commit>>} {<KeyPress-Return>}"
+            )),
+            mock.last_command()
+        );
+
+        mock.reply("event info", "<<Commit>> <<Copy>>");
+        assert_eq!(
+            vec![
+                EventPattern::virtual_event("Commit"),
+                EventPattern::virtual_event("Copy")
+            ],
+            virtual_events()
+        );
+        mock.reply("event info {<<Copy>>}", "<Control-Key-c> <Key-F16>");
+        assert_eq!(
+            vec![EventPattern::key("c").control(), EventPattern::key("F16")],
+            virtual_event_sequences("<<Copy>>")
+        );
+    }
+
+    #[test]
+    fn generate_events() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let root = session.root();
+
+        root.generate_event("<<CandidateChosen>>")
+            .data("ɛ b")
+            .generate()
+            .unwrap();
+        root.generate_event(EventKind::KeyPress)
+            .keysym("a")
+            .position(4, 2)
+            .when(When::Tail)
+            .generate()
+            .unwrap();
+        assert_eq!(
+            vec![
+                String::from("event generate . {<<CandidateChosen>>} -data {ɛ b}"),
+                String::from("event generate . {<KeyPress>} -keysym {a} -when tail -x 4 -y 2"),
+            ],
+            mock.commands()
+        );
+    }
+
+    #[test]
+    fn bind_invalid_pattern() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let root = session.root();
+        mock.clear();
+
+        // -- patterns which cannot be parsed are not sent to wish
+        let result = root.try_bind("<Contrl-Button-1>", |_| {});
+        assert!(matches!(
+            result,
+            Err(wish::TkError::InvalidPattern { pattern }) if pattern == "<Contrl-Button-1>"
+        ));
+        assert!(root
+            .try_bind_filter("<Button-7>", |_| widget::Propagation::Continue)
+            .is_err());
+        assert!(root.generate_event("<Enter-a>").generate().is_err());
+        assert!(mock.commands().is_empty());
+
+        let handle = root.try_bind("<Control-Button-1>", |_| {}).unwrap();
+        assert!(handle.is_bound());
+        assert!(mock
+            .last_command()
+            .unwrap()
+            .starts_with("bind {.} {<Control-ButtonPress-1>} {+afrish::bound "));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid event pattern \"<Contrl-Button-1>\"")]
+    fn bind_invalid_pattern_panics() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        session.root().bind("<Contrl-Button-1>", |_| {});
+    }
+
+    #[test]
+    #[should_panic]
+    fn build_pattern_invalid() {
+        let _ = EventPattern::new(EventKind::Enter).detail("a");
+    }
+}
@@ -28,6 +28,7 @@
 use std::fmt;
 use std::str;
 
+use super::tcl;
 use super::wish;
 
 /// Defines possible weights for font: normal and bold.
@@ -100,29 +101,20 @@ impl str::FromStr for TkFontMetrics {
     /// Parse from the {-option value} representation for font metrics,
     /// as described in 5th point of Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/font.htm#M19)
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut font = TkFontMetrics {
-            ..Default::default()
+        let options = s.parse::<tcl::TclDict>().map_err(|_| ParseFontMetricsErr)?;
+        let number = |key| {
+            options
+                .get(key)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or(0)
         };
 
-        for part in s.split('-') {
-            if part.starts_with("ascent") {
-                if let Ok(size) = &part[7..].trim().parse::<u64>() {
-                    font.ascent = *size;
-                }
-            } else if part.starts_with("descent") {
-                if let Ok(size) = &part[8..].trim().parse::<u64>() {
-                    font.descent = *size;
-                }
-            } else if part.starts_with("linespace") {
-                if let Ok(size) = &part[10..].trim().parse::<u64>() {
-                    font.line_space = *size;
-                }
-            } else if part.starts_with("fixed 1") {
-                font.fixed = true;
-            }
-        }
-
-        Ok(font)
+        Ok(TkFontMetrics {
+            ascent: number("-ascent"),
+            descent: number("-descent"),
+            line_space: number("-linespace"),
+            fixed: options.get("-fixed") == Some("1"),
+        })
     }
 }
 
@@ -141,7 +133,7 @@ impl fmt::Display for TkFont {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut font = String::new();
 
-        font.push_str(&format!("-family {{{}}} ", self.family));
+        font.push_str(&format!("-family {} ", tcl::quote(&self.family)));
         font.push_str(&format!("-size {} ", self.size));
         font.push_str(&format!("-weight {} ", self.weight));
         font.push_str(&format!("-slant {} ", self.slant));
@@ -168,37 +160,27 @@ impl str::FromStr for TkFont {
     /// Parse from the {-option value} representation for fonts,
     /// as described in 5th point of Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/font.htm#M13)
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut font = TkFont {
-            ..Default::default()
-        };
-
-        for part in s.split('-') {
-            if part.starts_with("family") {
-                let mut family = String::from(&part[7..]);
-                if let Some(index) = family.find('{') {
-                    family.remove(index);
-                }
-                if let Some(index) = family.find('}') {
-                    family.remove(index);
-                }
-                family = String::from(family.trim());
-                font.family = family;
-            } else if part.starts_with("size") {
-                if let Ok(size) = &part[5..].trim().parse::<u64>() {
-                    font.size = *size;
-                }
-            } else if part.starts_with("weight bold") {
-                font.weight = Weight::Bold;
-            } else if part.starts_with("slant italic") {
-                font.slant = Slant::Italic;
-            } else if part.starts_with("underline 1") {
-                font.underline = true;
-            } else if part.starts_with("overstrike 1") {
-                font.overstrike = true;
-            }
-        }
-
-        Ok(font)
+        let options = s.parse::<tcl::TclDict>().map_err(|_| ParseFontErr)?;
+
+        Ok(TkFont {
+            family: String::from(options.get("-family").unwrap_or_default()),
+            size: options
+                .get("-size")
+                .and_then(|size| size.parse::<u64>().ok())
+                .unwrap_or(0),
+            weight: if options.get("-weight") == Some("bold") {
+                Weight::Bold
+            } else {
+                Weight::Normal
+            },
+            slant: if options.get("-slant") == Some("italic") {
+                Slant::Italic
+            } else {
+                Slant::Roman
+            },
+            underline: options.get("-underline") == Some("1"),
+            overstrike: options.get("-overstrike") == Some("1"),
+        })
     }
 }
 
@@ -206,21 +188,17 @@ impl TkFont {
     /// Returns width in pixels of text if displayed with this font.
     pub fn measure(&self, text: &str) -> u64 {
         let msg = format!(
-            "puts [font measure {{{}}} {{{}}}] ; flush stdout",
-            self, text
+            "font measure {} {}",
+            tcl::quote(&self.to_string()),
+            tcl::quote(text)
         );
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            // TODO can this fail?
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Returns font's metrics
     pub fn metrics(&self) -> TkFontMetrics {
-        let msg = format!("puts [font metrics {{{}}}] ; flush stdout", self);
+        let msg = format!("font metrics {}", tcl::quote(&self.to_string()));
         let result = wish::ask_wish(&msg);
         if let Ok(value) = result.parse::<TkFontMetrics>() {
             value
@@ -236,7 +214,7 @@ impl TkFont {
 // Returns a font definition obtained by reading font
 // description for named font from wish.
 fn font_from_name(name: &str) -> TkFont {
-    let msg = format!("puts [font actual {}] ; flush stdout", name);
+    let msg = format!("font actual {}", tcl::quote(name));
     let result = wish::ask_wish(&msg);
 
     // assume this cannot error
@@ -301,8 +279,8 @@ pub fn tk_tooltip_font() -> TkFont {
 
 /// Return list of font families available on current platform.
 pub fn font_families() -> Vec<String> {
-    let result = wish::ask_wish("puts [font families] ; flush stdout");
-    wish::split_items(&result)
+    let result = wish::ask_wish("font families");
+    tcl::list_items(&result)
 }
 
 #[cfg(test)]
@@ -352,6 +330,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn str_to_font_3() {
+        // -- family names may contain braces, and sizes may be in pixels
+        let font_str = "-family {Noto {Sans} Ɛ} -size -12 -weight bold -slant roman -underline 1 -overstrike 0";
+        let font = font_str.parse::<TkFont>().unwrap();
+        assert_eq!(
+            TkFont {
+                family: String::from("Noto {Sans} Ɛ"),
+                weight: Weight::Bold,
+                underline: true,
+                ..Default::default()
+            },
+            font
+        );
+        assert!("-family {Noto".parse::<TkFont>().is_err());
+    }
+
+    #[test]
+    fn str_to_metrics() {
+        let metrics = "-ascent 15 -descent 4 -linespace 19 -fixed 1"
+            .parse::<TkFontMetrics>()
+            .unwrap();
+        assert_eq!(
+            TkFontMetrics {
+                ascent: 15,
+                descent: 4,
+                line_space: 19,
+                fixed: true,
+            },
+            metrics
+        );
+    }
+
     #[test]
     fn str_to_font_2() {
         let font_str = "-family {Helvetica Special} -size 14 -weight normal -slant italic -underline 0 -overstrike 1";
@@ -13,7 +13,7 @@
 //! 1. `grid` is called first, to get the GridLayout instance.
 //! 2. `layout` must be called last, to perform the layout.
 //! 3. zero or more options are added to the GridLayout, to control the position
-//!   and layout of the widget.
+//!    and layout of the widget.
 //!
 //! ## Example
 //!
@@ -30,6 +30,7 @@
 //! ```
 //!
 
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -179,7 +180,12 @@ pub trait TkGridLayout: widget::TkWidget {
     /// ```
     ///
     fn grid_configure(&self, option: &str, value: &str) {
-        let msg = format!("grid configure {} -{} {{{}}}", self.id(), option, value);
+        let msg = format!(
+            "grid configure {} -{} {}",
+            self.id(),
+            option,
+            tcl::quote(value)
+        );
         wish::tell_wish(&msg);
     }
 
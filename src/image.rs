@@ -1,6 +1,7 @@
 //! Images - method to read in an image from file.
 //!
 
+use super::tcl;
 use super::wish;
 
 /// Refers to an image
@@ -12,7 +13,7 @@ pub struct TkImage {
 /// Reads an image from a given filename and returns the image reference.
 pub fn read_image(filename: &str) -> TkImage {
     let id = wish::next_wid(".");
-    let msg = format!("image create photo {} -file {}", id, filename);
+    let msg = format!("image create photo {} -file {}", id, tcl::quote(filename));
     wish::tell_wish(&msg);
 
     TkImage { id }
@@ -33,7 +33,7 @@
 //!
 //!   hello.grid().layout();
 //!
-//!   afrish::mainloop();
+//!   afrish::mainloop().unwrap();
 //! }
 //! ```
 //!
@@ -50,6 +50,9 @@
 pub mod button;
 pub use button::*;
 
+pub mod candidate_list;
+pub use candidate_list::*;
+
 pub mod canvas;
 pub use canvas::*;
 
@@ -65,6 +68,9 @@ pub use dialog::*;
 pub mod entry;
 pub use entry::*;
 
+pub mod event;
+pub use event::*;
+
 pub mod font;
 pub use font::*;
 
@@ -86,9 +92,15 @@ pub use label_frame::*;
 pub mod listbox;
 pub use listbox::*;
 
+pub mod local;
+pub use local::*;
+
 pub mod menu;
 pub use menu::*;
 
+pub mod mock;
+pub use mock::*;
+
 pub mod notebook;
 pub use notebook::*;
 
@@ -98,6 +110,9 @@ pub use pack::*;
 pub mod paned_window;
 pub use paned_window::*;
 
+pub mod popup_window;
+pub use popup_window::*;
+
 pub mod progressbar;
 pub use progressbar::*;
 
@@ -116,12 +131,21 @@ pub use separator::*;
 pub mod spinbox;
 pub use spinbox::*;
 
+pub mod state_store;
+pub use state_store::*;
+
+pub mod tcl;
+pub use tcl::*;
+
 pub mod text;
 pub use text::*;
 
 pub mod theme;
 pub use theme::*;
 
+pub mod timer;
+pub use timer::*;
+
 pub mod toplevel;
 pub use toplevel::*;
 
@@ -11,6 +11,7 @@
 
 use super::grid;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -30,7 +31,7 @@ pub fn make_listbox(parent: &impl widget::TkWidget, values: &[&str]) -> TkListbo
 
     // - add values to listbox
     for value in values {
-        let msg = format!("{} insert end {{{}}}", id, value);
+        let msg = format!("{} insert end {}", id, tcl::quote(value));
         wish::tell_wish(&msg);
     }
     // - select first item at start
@@ -53,7 +54,7 @@ impl pack::TkPackLayout for TkListbox {}
 impl TkListbox {
     /// Adds item to end of list.
     pub fn append(&self, item: &str) {
-        let msg = format!("{} insert end {{{}}}", &self.id, item);
+        let msg = format!("{} insert end {}", &self.id, tcl::quote(item));
         wish::tell_wish(&msg);
     }
 
@@ -80,19 +81,40 @@ impl TkListbox {
 
     /// Insert item at given index.
     pub fn insert_at(&self, index: u64, item: &str) {
-        let msg = format!("{} insert {} {{{}}}", &self.id, index, item);
+        let msg = format!("{} insert {} {}", &self.id, index, tcl::quote(item));
         wish::tell_wish(&msg);
     }
 
     /// Set configuration option for given item index.
     pub fn item_configure(&self, index: u64, option: &str, value: &str) {
         let msg = format!(
-            "{} itemconfigure {} -{} {{{}}}",
-            &self.id, index, option, value
+            "{} itemconfigure {} -{} {}",
+            &self.id,
+            index,
+            option,
+            tcl::quote(value)
         );
         wish::tell_wish(&msg);
     }
 
+    /// Set configuration option for given item index, returning the
+    /// error reported by Tk if the index, option or value is invalid.
+    pub fn try_item_configure(
+        &self,
+        index: u64,
+        option: &str,
+        value: &str,
+    ) -> Result<(), wish::TkError> {
+        let msg = format!(
+            "{} itemconfigure {} -{} {}",
+            &self.id,
+            index,
+            option,
+            tcl::quote(value)
+        );
+        wish::try_tell_wish(&msg)
+    }
+
     /// Alignment of text within widget.
     pub fn justify(&self, value: widget::Justify) {
         widget::configure(&self.id, "justify", &value.to_string());
@@ -115,11 +137,11 @@ impl TkListbox {
 
     /// Returns list of indices for selected items.
     pub fn selected_items(&self) -> Vec<u64> {
-        let query = format!("puts [{} curselection] ; flush stdout", &self.id);
+        let query = format!("{} curselection", &self.id);
         let values = wish::ask_wish(&query);
 
         let mut result: Vec<u64> = vec![];
-        for value in values.split_whitespace() {
+        for value in tcl::list_items(&values) {
             if let Ok(value) = value.parse::<u64>() {
                 result.push(value);
             }
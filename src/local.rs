@@ -0,0 +1,222 @@
+//! Local callbacks - callbacks which need not be `Send`, and application
+//! state shared by callbacks.
+//!
+//! Callbacks are always called on the thread running the mainloop, but
+//! the methods registering them require `Send` closures. Wrapping a
+//! closure with [local_command] or [local_handler] keeps it on the current
+//! thread, so it may capture `Rc<RefCell<..>>` values:
+//!
+//! ```ignore
+//! let count = Rc::new(RefCell::new(0));
+//! let label = afrish::make_label(&root);
+//! button.command(afrish::local_command(move || {
+//!     *count.borrow_mut() += 1;
+//!     label.text(&count.borrow().to_string());
+//! }));
+//! ```
+//!
+//! Alternatively, a program's state can be handed to
+//! [mainloop_with](crate::wish::mainloop_with), and callbacks made with
+//! [app_command] or [app_handler] are given a mutable reference to it:
+//!
+//! ```ignore
+//! struct App { count: u32 }
+//!
+//! button.command(afrish::app_command(|app: &mut App| app.count += 1));
+//! canvas.bind("<Button-1>", afrish::app_handler(|app: &mut App, event: afrish::TkEvent| {
+//!     println!("{} clicks, the last at {},{}", app.count, event.x, event.y);
+//! }));
+//!
+//! let (_reason, app) = afrish::mainloop_with(App { count: 0 }).unwrap();
+//! ```
+//!
+//! Local callbacks must be called on the thread which created them, so
+//! the mainloop must run on that thread.
+//!
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+thread_local! {
+    // Local callbacks created on this thread, by id.
+    static LOCAL_CALLBACKS: RefCell<HashMap<u64, Box<dyn Any>>> = RefCell::new(HashMap::new());
+    // The state given to mainloop_with, while it runs on this thread.
+    static APP_STATE: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+}
+
+// Refers to a local callback, removing it when dropped on its thread:
+// elsewhere, the callback is dropped when its thread ends.
+struct LocalRef {
+    id: u64,
+    thread: thread::ThreadId,
+}
+
+impl LocalRef {
+    fn new<A: 'static>(command: Rc<dyn Fn(A)>) -> LocalRef {
+        static NEXT_LOCAL: AtomicU64 = AtomicU64::new(1);
+
+        let id = NEXT_LOCAL.fetch_add(1, Ordering::Relaxed);
+        LOCAL_CALLBACKS.with(|callbacks| callbacks.borrow_mut().insert(id, Box::new(command)));
+
+        LocalRef {
+            id,
+            thread: thread::current().id(),
+        }
+    }
+
+    fn call<A: 'static>(&self, value: A) {
+        assert!(
+            self.thread == thread::current().id(),
+            "local callback called from another thread"
+        );
+        // -- clone the function, so it can register or drop callbacks
+        let command = LOCAL_CALLBACKS.with(|callbacks| {
+            callbacks
+                .borrow()
+                .get(&self.id)
+                .and_then(|command| command.downcast_ref::<Rc<dyn Fn(A)>>())
+                .cloned()
+        });
+        if let Some(command) = command {
+            command(value);
+        }
+    }
+}
+
+impl Drop for LocalRef {
+    fn drop(&mut self) {
+        if self.thread == thread::current().id() {
+            let _ = LOCAL_CALLBACKS.try_with(|callbacks| callbacks.borrow_mut().remove(&self.id));
+        }
+    }
+}
+
+/// Wraps a command which need not be `Send`, e.g. one capturing an
+/// `Rc`, so it can be used as a button's command or a timer.
+///
+/// # Panics
+///
+/// The returned command panics if called on a different thread to the
+/// one which created it.
+pub fn local_command(command: impl Fn() + 'static) -> impl Fn() + Send + 'static {
+    let local = LocalRef::new::<()>(Rc::new(move |()| command()));
+    move || local.call(())
+}
+
+/// Wraps a callback taking an argument, such as a bound event, which need
+/// not be `Send`.
+///
+/// # Panics
+///
+/// The returned callback panics if called on a different thread to the
+/// one which created it.
+pub fn local_handler<A: 'static>(command: impl Fn(A) + 'static) -> impl Fn(A) + Send + 'static {
+    let local = LocalRef::new::<A>(Rc::new(command));
+    move |value| local.call(value)
+}
+
+/// Wraps a command taking the state given to
+/// [mainloop_with](crate::wish::mainloop_with).
+///
+/// The command is not called if no state of type `S` is available, e.g.
+/// when events are handled by [process_pending](crate::wish::process_pending).
+pub fn app_command<S: 'static>(command: impl Fn(&mut S) + 'static) -> impl Fn() + Send + 'static {
+    local_command(move || {
+        with_app_state(|state: &mut S| command(state));
+    })
+}
+
+/// Wraps a callback taking the state given to
+/// [mainloop_with](crate::wish::mainloop_with) and an argument, such as a
+/// bound event.
+pub fn app_handler<S: 'static, A: 'static>(
+    command: impl Fn(&mut S, A) + 'static,
+) -> impl Fn(A) + Send + 'static {
+    local_handler(move |value| {
+        with_app_state(|state: &mut S| command(state, value));
+    })
+}
+
+/// Calls given function with the state given to
+/// [mainloop_with](crate::wish::mainloop_with), returning its result.
+///
+/// Returns `None` if no state of type `S` is available, or if the state is
+/// already in use, as when called from within `with_app_state`.
+pub fn with_app_state<S: 'static, R>(function: impl FnOnce(&mut S) -> R) -> Option<R> {
+    APP_STATE.with(|state| {
+        let mut state = state.try_borrow_mut().ok()?;
+        let state = state.as_mut()?.downcast_mut::<S>()?;
+        Some(function(state))
+    })
+}
+
+// Makes given state available to callbacks on this thread, returning any
+// state it replaces.
+pub(super) fn set_app_state(state: Option<Box<dyn Any>>) -> Option<Box<dyn Any>> {
+    APP_STATE.with(|app_state| std::mem::replace(&mut *app_state.borrow_mut(), state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+    use crate::wish::ExitReason;
+
+    #[test]
+    fn local_callbacks() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let button = crate::make_button(&session.root());
+        let count = Rc::new(RefCell::new(0));
+        let count_clone = count.clone();
+        let handle = button.command(local_command(move || *count_clone.borrow_mut() += 1));
+
+        mock.event(&format!("clicked-{}", button.id));
+        mock.event(&format!("clicked-{}", button.id));
+        session.process_pending().unwrap();
+        assert_eq!(2, *count.borrow());
+
+        // -- unregistering drops the closure
+        handle.unregister();
+        assert_eq!(1, Rc::strong_count(&count));
+    }
+
+    #[test]
+    fn app_state() {
+        struct App {
+            clicks: u32,
+            last_x: i64,
+        }
+
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let button = crate::make_button(&session.root());
+        button.command(app_command(|app: &mut App| app.clicks += 1));
+        let binding = crate::widget::bind(
+            "<Button-1>",
+            app_handler(|app: &mut App, event: crate::widget::TkEvent| app.last_x = event.x),
+        );
+
+        mock.event(&format!("clicked-{}", button.id));
+        mock.event(&format!(
+            "cb1e {{{}}} 10 20 110 120 5 6 0 ?? 1",
+            binding.key()
+        ));
+        mock.close();
+        let (reason, app) = session
+            .mainloop_with(App {
+                clicks: 0,
+                last_x: 0,
+            })
+            .unwrap();
+        assert!(matches!(reason, ExitReason::WishDied(_)));
+        assert_eq!((1, 10), (app.clicks, app.last_x));
+        assert_eq!(None, with_app_state(|app: &mut App| app.clicks));
+    }
+}
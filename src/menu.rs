@@ -18,6 +18,7 @@
 //!
 
 use super::image;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -182,13 +183,13 @@ impl TkMenu {
 
     /// Returns the value for a given radio-button group.
     pub fn radio_button_value_get(&self, group: &str) -> String {
-        let msg = format!("puts $::mrb_group_{} ; flush stdout", group);
+        let msg = format!("set {}", group_variable(group));
         wish::ask_wish(&msg)
     }
 
     /// Sets the value for a given radio-button group.
     pub fn radio_button_value(&self, group: &str, value: &str) {
-        let msg = format!("set ::mrb_group_{} {}", group, value);
+        let msg = format!("set {} {}", group_variable(group), tcl::quote(value));
         wish::tell_wish(&msg);
     }
 
@@ -208,20 +209,49 @@ impl TkMenu {
     /// Returns the value (as a String) for given option for
     /// menu-item at given index position.
     pub fn entry_cget(&self, index: u64, option: &str) -> String {
-        let msg = format!("{} entrycfig {} {{{}}}", &self.id, index, option);
+        let msg = format!("{} entrycget {} {}", &self.id, index, tcl::quote(option));
         wish::ask_wish(&msg)
     }
 
+    /// Returns the value (as a String) for given option for
+    /// menu-item at given index position, or the error reported by Tk.
+    pub fn try_entry_cget(&self, index: u64, option: &str) -> Result<String, wish::TkError> {
+        let msg = format!("{} entrycget {} {}", &self.id, index, tcl::quote(option));
+        wish::try_ask_wish(&msg)
+    }
+
     /// Sets the value (as a String) for given option for
     /// menu-item at given index position.
     pub fn entry_configure(&self, index: u64, option: &str, value: &str) {
         let msg = format!(
-            "{} entryconfigure {} {{{}}} {{{}}}",
-            &self.id, index, option, value
+            "{} entryconfigure {} {} {}",
+            &self.id,
+            index,
+            tcl::quote(option),
+            tcl::quote(value)
         );
         wish::tell_wish(&msg);
     }
 
+    /// Sets the value (as a String) for given option for
+    /// menu-item at given index position, returning the error
+    /// reported by Tk if the index, option or value is invalid.
+    pub fn try_entry_configure(
+        &self,
+        index: u64,
+        option: &str,
+        value: &str,
+    ) -> Result<(), wish::TkError> {
+        let msg = format!(
+            "{} entryconfigure {} {} {}",
+            &self.id,
+            index,
+            tcl::quote(option),
+            tcl::quote(value)
+        );
+        wish::try_tell_wish(&msg)
+    }
+
     /// Invokes any associated command for the menu-item at given index
     /// position.
     pub fn invoke(&self, index: u64) {
@@ -236,6 +266,11 @@ impl TkMenu {
     }
 }
 
+// Returns the variable holding the value of a radio-button group.
+fn group_variable(group: &str) -> String {
+    tcl::quote(&format!("::mrb_group_{}", group))
+}
+
 // Convert core options into a string representation
 fn common_option_string(
     compound: &widget::Compound,
@@ -249,13 +284,13 @@ fn common_option_string(
 
     msg.push_str(&format!("-compound {} ", compound));
     if let Some(font) = &font {
-        msg.push_str(&format!("-font {{{}}} ", font));
+        msg.push_str(&format!("-font {} ", tcl::quote(font)));
     }
     if let Some(image) = &image {
-        msg.push_str(&format!("-image {{{}}} ", image));
+        msg.push_str(&format!("-image {} ", tcl::quote(image)));
     }
     if let Some(label) = &label {
-        msg.push_str(&format!("-label {{{}}} ", label));
+        msg.push_str(&format!("-label {} ", tcl::quote(label)));
     }
     msg.push_str(&format!("-state {} ", state));
     if let Some(underline) = &underline {
@@ -355,7 +390,7 @@ impl TkMenuCheck {
     pub fn command(&mut self, command: impl Fn(bool) + Send + 'static) -> &mut Self {
         let id = wish::next_wid(".");
         let var = format!("::mcb{}", wish::current_id());
-        wish::add_callback1_bool(&id, wish::mk_callback1_bool(command));
+        wish::add_callback(&self.parent, &id, command);
         self.command = Some(id);
         self.command_variable = Some(var);
         self
@@ -409,12 +444,12 @@ impl TkMenuCheck {
         );
 
         if let Some(accelerator) = &self.accelerator {
-            msg.push_str(&format!("-accelerator {{{}}} ", &accelerator));
+            msg.push_str(&format!("-accelerator {} ", tcl::quote(accelerator)));
         }
         if let Some(command) = &self.command {
             if let Some(command_variable) = &self.command_variable {
                 msg.push_str(&format!(
-                    "-command {{ puts cb1b-{}-${} ; flush stdout }} ",
+                    "-command {{ afrish::event cb1b-{}-${} }} ",
                     &command, &command_variable
                 ));
                 msg.push_str(&format!("-variable {} ", &command_variable));
@@ -452,7 +487,7 @@ impl TkMenuCommand {
     /// Sets command to invoke when menu-item clicked.
     pub fn command(&mut self, command: impl Fn() + Send + 'static) -> &mut Self {
         let id = wish::next_wid(".");
-        wish::add_callback0(&id, wish::mk_callback0(command));
+        wish::add_callback(&self.parent, &id, move |()| command());
         self.command = Some(id);
         self
     }
@@ -505,11 +540,11 @@ impl TkMenuCommand {
         );
 
         if let Some(accelerator) = &self.accelerator {
-            msg.push_str(&format!("-accelerator {{{}}} ", &accelerator));
+            msg.push_str(&format!("-accelerator {} ", tcl::quote(accelerator)));
         }
         if let Some(command) = &self.command {
             msg.push_str(&format!(
-                "-command {{ puts clicked-{} ; flush stdout }} ",
+                "-command {{ afrish::event clicked-{} }} ",
                 &command
             ));
         }
@@ -546,7 +581,7 @@ impl TkMenuRadio {
     pub fn command(&mut self, command: impl Fn(bool) + Send + 'static) -> &mut Self {
         let id = wish::next_wid(".");
         let var = format!("::mcb{}", wish::current_id());
-        wish::add_callback1_bool(&id, wish::mk_callback1_bool(command));
+        wish::add_callback(&self.parent, &id, command);
         self.command = Some(id);
         self.command_variable = Some(var);
         self
@@ -600,19 +635,20 @@ impl TkMenuRadio {
         );
 
         msg.push_str(&format!(
-            "-variable ::mrb_group_{} -value {{{}}} ",
-            &self.group, &self.value
+            "-variable {} -value {} ",
+            group_variable(&self.group),
+            tcl::quote(&self.value)
         ));
         if let Some(accelerator) = &self.accelerator {
-            msg.push_str(&format!("-accelerator {{{}}} ", &accelerator));
+            msg.push_str(&format!("-accelerator {} ", tcl::quote(accelerator)));
         }
         if let Some(command) = &self.command {
             if let Some(command_variable) = &self.command_variable {
                 msg.push_str(&format!(
-                    "-command {{ puts cb1b-{}-{} ; flush stdout }} ",
+                    "-command {{ afrish::event cb1b-{}-{} }} ",
                     &command, &command_variable
                 ));
-                msg.push_str(&format!("-variable {{{}}} ", &command_variable));
+                msg.push_str(&format!("-variable {} ", tcl::quote(command_variable)));
             }
         }
 
@@ -646,7 +682,102 @@ impl TkMenuSeparator {
 
     /// Inserts separator into parent at given index.
     pub fn insert(&self, index: u64) {
-        let msg = format!("{} insert sepator {}", &self.parent, index);
+        let msg = format!("{} insert separator {}", &self.parent, index);
         wish::tell_wish(&msg);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn add_command() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let menu = make_menu(&session.root());
+        assert_eq!(Some(format!("menu {}", menu.id)), mock.last_command());
+
+        let invoked = Arc::new(AtomicBool::new(false));
+        let invoked_clone = invoked.clone();
+        menu.command()
+            .label("Open")
+            .accelerator("Ctrl-O")
+            .underline(0)
+            .command(move || invoked_clone.store(true, Ordering::SeqCst))
+            .add();
+        let command = mock.last_command().unwrap();
+        assert!(command.starts_with(&format!(
+            "{} add command -compound none -label {{Open}} -state normal -underline 0 -accelerator {{Ctrl-O}} -command {{ afrish::event clicked-",
+            menu.id
+        )));
+
+        // -- the event named in the command calls the closure
+        let event = command
+            .split("afrish::event ")
+            .nth(1)
+            .unwrap()
+            .trim_end_matches([' ', '}']);
+        mock.event(event);
+        session.process_pending().unwrap();
+        assert!(invoked.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn cascade_and_separator() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let menubar = make_menu(&session.root());
+        let file = make_menu(&menubar);
+
+        mock.clear();
+        menubar.cascade().menu(&file).label("File").add();
+        file.separator().insert(2);
+        file.radio_button("size", "small").label("Small").add();
+        assert_eq!(
+            vec![
+                format!(
+                    "{} add cascade -compound none -label {{File}} -state normal -menu {} ",
+                    menubar.id, file.id
+                ),
+                format!("{} insert separator 2", file.id),
+                format!(
+                    "{} add radiobutton -compound none -label {{Small}} -state normal -variable {{::mrb_group_size}} -value {{small}} ",
+                    file.id
+                ),
+            ],
+            mock.commands()
+        );
+    }
+
+    #[test]
+    fn entries() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let menu = make_menu(&session.root());
+
+        mock.reply(&format!("{} entrycget 1 {{-label}}", menu.id), "Save");
+        assert_eq!("Save", menu.entry_cget(1, "-label"));
+
+        mock.fail(&format!("{} entrycget 9 {{-label}}", menu.id), "bad index");
+        assert!(matches!(
+            menu.try_entry_cget(9, "-label"),
+            Err(wish::TkError::Tcl { message, .. }) if message == "bad index"
+        ));
+
+        menu.entry_configure(1, "-state", "disabled");
+        assert_eq!(
+            Some(format!(
+                "{} entryconfigure 1 {{-state}} {{disabled}}",
+                menu.id
+            )),
+            mock.last_command()
+        );
+    }
+}
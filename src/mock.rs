@@ -0,0 +1,200 @@
+//! Mock wish - a transport for testing code without running wish.
+//!
+//! A [MockWish] records every Tcl command sent to it, and replies to
+//! queries with scripted results, so the commands produced by widgets can
+//! be checked without a wish program or display:
+//!
+//! ```
+//! use afrish::*;
+//!
+//! let mock = afrish::MockWish::new();
+//! let session = mock.session();
+//! let _guard = session.enter();
+//!
+//! let button = afrish::make_button(&session.root());
+//! button.text("x");
+//! assert_eq!(
+//!     Some(format!("{} configure -text {{x}}", button.id)),
+//!     mock.last_command()
+//! );
+//! ```
+//!
+//! Events can be sent to the session with [event](MockWish::event), and
+//! are handled as usual by the mainloop or [process_pending](crate::process_pending).
+//!
+
+use std::collections::HashMap;
+use std::process;
+#[cfg(test)]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(test)]
+use std::sync::OnceLock;
+use std::sync::{Arc, Mutex};
+
+use super::wish;
+
+/// A transport recording commands and returning scripted replies.
+#[derive(Clone, Debug, Default)]
+pub struct MockWish {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    commands: Vec<String>,
+    replies: HashMap<String, Result<String, String>>,
+    events: Option<wish::EventSink>,
+    finished: bool,
+}
+
+impl MockWish {
+    /// Creates a new mock, with no recorded commands or replies.
+    pub fn new() -> MockWish {
+        MockWish::default()
+    }
+
+    /// Creates a session using this mock as its transport.
+    pub fn session(&self) -> wish::TkSession {
+        wish::TkSession::with_transport(self.clone())
+    }
+
+    /// Sets the result returned when given command is queried: other
+    /// queries return an empty string.
+    pub fn reply(&self, command: &str, result: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .replies
+            .insert(String::from(command), Ok(String::from(result)));
+    }
+
+    /// Makes given command fail with a Tcl error, holding given message.
+    pub fn fail(&self, command: &str, message: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .replies
+            .insert(String::from(command), Err(String::from(message)));
+    }
+
+    /// Returns all commands sent so far, in order.
+    pub fn commands(&self) -> Vec<String> {
+        self.state.lock().unwrap().commands.clone()
+    }
+
+    /// Returns the most recent command sent.
+    pub fn last_command(&self) -> Option<String> {
+        self.state.lock().unwrap().commands.last().cloned()
+    }
+
+    /// Forgets the commands sent so far.
+    pub fn clear(&self) {
+        self.state.lock().unwrap().commands.clear();
+    }
+
+    /// Sends an event message to the session, as `afrish::event` would.
+    pub fn event(&self, message: &str) {
+        let events = self.state.lock().unwrap().events.clone();
+        if let Some(events) = events {
+            events.event(message);
+        }
+    }
+
+    /// Behaves as if wish has exited.
+    pub fn close(&self) {
+        let events = {
+            let mut state = self.state.lock().unwrap();
+            if state.finished {
+                return;
+            }
+            state.finished = true;
+            state.events.clone()
+        };
+        if let Some(events) = events {
+            events.close();
+        }
+    }
+}
+
+impl wish::Transport for MockWish {
+    fn attach(&self, events: wish::EventSink) {
+        self.state.lock().unwrap().events = Some(events);
+    }
+
+    fn tell(&self, msg: &str) {
+        self.state.lock().unwrap().commands.push(String::from(msg));
+    }
+
+    fn ask(&self, msg: &str, reply: wish::Reply) -> bool {
+        let result = {
+            let mut state = self.state.lock().unwrap();
+            if state.finished {
+                return false;
+            }
+            state.commands.push(String::from(msg));
+            state
+                .replies
+                .get(msg)
+                .cloned()
+                .unwrap_or_else(|| Ok(String::new()))
+        };
+        reply(result.map_err(|message| wish::TkError::Tcl {
+            error_info: message.clone(),
+            message,
+        }));
+
+        true
+    }
+
+    fn is_connected(&self) -> bool {
+        !self.state.lock().unwrap().finished
+    }
+
+    fn end(&self) {
+        self.close();
+    }
+
+    fn wait(&self) -> Result<process::ExitStatus, wish::TkError> {
+        Ok(process::ExitStatus::default())
+    }
+
+    fn exit_status(&self) -> Option<process::ExitStatus> {
+        if self.state.lock().unwrap().finished {
+            Some(process::ExitStatus::default())
+        } else {
+            None
+        }
+    }
+}
+
+// Counts the calls of the returned command.
+#[cfg(test)]
+pub(crate) fn counter() -> (Arc<AtomicU64>, impl Fn() + Send + 'static) {
+    let count = Arc::new(AtomicU64::new(0));
+    let count_clone = count.clone();
+    (count, move || {
+        count_clone.fetch_add(1, Ordering::SeqCst);
+    })
+}
+
+// A session for checking scripts in a real Tcl interpreter, such as
+// quoted text or the framing procs: tclsh is preferred, needing no
+// display, with Tk commands stubbed as needed.
+//
+// Panics if neither tclsh nor wish can be started, so the tests are never
+// passed without being checked.
+#[cfg(test)]
+pub(crate) fn tcl_session() -> &'static wish::TkSession {
+    static SESSION: OnceLock<Option<wish::TkSession>> = OnceLock::new();
+
+    SESSION
+        .get_or_init(|| {
+            ["tclsh", "wish"].iter().find_map(|program| {
+                let session = wish::TkSession::start_with(program).ok()?;
+                session.try_ask_wish("info patchlevel").ok()?;
+                Some(session)
+            })
+        })
+        .as_ref()
+        .expect("these tests need tclsh (or wish) on the PATH")
+}
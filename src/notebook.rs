@@ -11,6 +11,7 @@
 
 use super::grid;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -42,7 +43,12 @@ impl pack::TkPackLayout for TkNotebook {}
 impl TkNotebook {
     /// TODO: use builder pattern to support images+text
     pub fn add(&self, widget: &impl widget::TkWidget, title: &str) {
-        let msg = format!("{} add {} -text {{{}}}", self.id, widget.id(), title);
+        let msg = format!(
+            "{} add {} -text {}",
+            self.id,
+            widget.id(),
+            tcl::quote(title)
+        );
         wish::tell_wish(&msg);
     }
 
@@ -51,6 +57,19 @@ impl TkNotebook {
         widget::configure(&self.id, "height", &height.to_string());
     }
 
+    /// Selects the tab at given index, counting from 0.
+    pub fn select_tab(&self, index: u64) {
+        let msg = format!("{} select {}", self.id, index);
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns the index of the selected tab, or `None` if there are no
+    /// tabs.
+    pub fn selected_tab(&self) -> Option<u64> {
+        let msg = format!("{} index current", self.id);
+        wish::try_ask_wish(&msg).ok()?.parse::<u64>().ok()
+    }
+
     /// Sets space around the widget. Takes
     /// an array of up to four values, specifying:
     ///
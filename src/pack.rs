@@ -13,8 +13,9 @@
 //! 1. `pack` is called first, to get the PackLayout instance.
 //! 2. `layout` must be called last, to perform the layout.
 //! 3. zero or more options are added to the PackLayout, to control the position
-//!   and layout of the widget.
+//!    and layout of the widget.
 
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -173,7 +174,12 @@ pub trait TkPackLayout: widget::TkWidget {
 
     /// Sets properties for widget layout
     fn pack_configure(&self, option: &str, value: &str) {
-        let msg = format!("pack configure {} -{} {{{}}}", self.id(), option, value);
+        let msg = format!(
+            "pack configure {} -{} {}",
+            self.id(),
+            option,
+            tcl::quote(value)
+        );
         wish::tell_wish(&msg);
     }
 
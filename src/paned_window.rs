@@ -5,6 +5,7 @@
 
 use super::grid;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -78,6 +79,25 @@ impl TkPanedWindow {
         wish::tell_wish(&msg);
     }
 
+    /// Returns the ids of the panes.
+    pub fn panes(&self) -> Vec<String> {
+        let msg = format!("{} panes", self.id);
+        tcl::list_items(&wish::ask_wish(&msg))
+    }
+
+    /// Moves the sash with given index, counting from 0, to given position
+    /// in pixels: the sash must be shown for the position to take effect.
+    pub fn sash_position(&self, index: u64, position: u64) {
+        let msg = format!("{} sashpos {} {}", self.id, index, position);
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns the position in pixels of the sash with given index.
+    pub fn sash_position_get(&self, index: u64) -> u64 {
+        let msg = format!("{} sashpos {}", self.id, index);
+        wish::ask_wish(&msg).parse::<u64>().unwrap_or(0)
+    }
+
     /// Width of paned window, in columns
     pub fn width(&self, width: u64) {
         widget::configure(&self.id, "width", &width.to_string());
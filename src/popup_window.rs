@@ -0,0 +1,150 @@
+//! Popup window - a borderless top-level window which does not take the
+//! keyboard focus, for tooltips, completion and candidate lists.
+//!
+//! A popup is created hidden. It is shown at a given position on screen,
+//! moved if needed to stay within the screen, and hidden again, each with
+//! one command sent to wish, so it can be shown and hidden as often as
+//! needed, e.g. on every keystroke:
+//!
+//! ```ignore
+//! let popup = afrish::make_popup_window(&root, afrish::WindowType::PopupMenu);
+//! let label = afrish::make_label(&popup);
+//! label.grid().layout();
+//!
+//! label.text("ɛ");
+//! popup.show_near(caret_x, caret_top, caret_bottom);
+//! ...
+//! popup.hide();
+//! ```
+//!
+//! The window is override-redirect, so the window manager neither
+//! decorates it nor gives it the focus, and it stays above other windows.
+//! The popup, and widgets within it, do not take the focus when traversing
+//! with the keyboard: widgets within it should not take the focus when
+//! clicked, e.g. use labels or a canvas rather than entries.
+//!
+//! The screen is the visible area used by
+//! [clamp_to_screen](crate::toplevel::TkTopLevel::clamp_to_screen): where
+//! several monitors form one X screen, it spans all of them, so a popup
+//! kept within it may still be in a part not shown on any monitor.
+//!
+//! Settings shared with other top-level windows, such as the background
+//! or window type, are made on its [toplevel](TkPopupWindow::toplevel):
+//!
+//! ```ignore
+//! popup.toplevel().background("ivory");
+//! ```
+//!
+//! * also see the Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/wm.htm#M48)
+
+use super::toplevel;
+use super::widget;
+use super::wish;
+
+/// Refers to a popup window
+#[derive(Clone, Debug, PartialEq)]
+pub struct TkPopupWindow {
+    pub id: String,
+}
+
+/// Creates a hidden popup window with given parent, of given type: on X11,
+/// compositors use the type to decide on shadows and animations.
+pub fn make_popup_window(
+    parent: &impl widget::TkWidget,
+    window_type: toplevel::WindowType,
+) -> TkPopupWindow {
+    let id = wish::next_wid(parent.id());
+    // -- one script, so the window is set up before Tk can show it
+    let msg = format!(
+        "toplevel {id} -takefocus 0; wm withdraw {id}; wm overrideredirect {id} 1; \
+         wm attributes {id} -topmost 1; {}; afrish::nofocus {id}",
+        toplevel::window_type_command(&id, window_type)
+    );
+    wish::tell_wish(&msg);
+
+    TkPopupWindow { id }
+}
+
+impl widget::TkWidget for TkPopupWindow {
+    /// Returns the widget's id reference - used within tk
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl TkPopupWindow {
+    /// Hides the popup.
+    pub fn hide(&self) {
+        let msg = format!("wm withdraw {}", self.id);
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns true if the popup is shown.
+    pub fn is_shown(&self) -> bool {
+        let msg = format!("winfo ismapped {}", self.id);
+        wish::ask_wish(&msg) == "1"
+    }
+
+    /// Shows the popup with its top-left corner at given screen
+    /// coordinates, moved if needed so all of it is within the screen.
+    pub fn show_at(&self, x: i64, y: i64) {
+        let msg = format!("afrish::popup {} {} {}", self.id, x, y);
+        wish::tell_wish(&msg);
+    }
+
+    /// Shows the popup next to a region of the screen, such as a text
+    /// caret: below the region at `bottom`, or above it at `top` if there is
+    /// no room below, moved across if needed to stay within the screen.
+    pub fn show_near(&self, x: i64, top: i64, bottom: i64) {
+        let msg = format!("afrish::popup {} {} {} {}", self.id, x, bottom, top);
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns the popup as a top-level window, for the settings it
+    /// shares with other windows. Its border, stacking and focus are set
+    /// by the popup, and should not be changed.
+    pub fn toplevel(&self) -> toplevel::TkTopLevel {
+        toplevel::TkTopLevel {
+            id: self.id.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+
+    #[test]
+    fn popup_window() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let popup = make_popup_window(&session.root(), toplevel::WindowType::Tooltip);
+        assert_eq!(
+            Some(format!(
+                "toplevel {id} -takefocus 0; wm withdraw {id}; wm overrideredirect {id} 1; \
+                 wm attributes {id} -topmost 1; \
+                 if {{[tk windowingsystem] eq {{x11}}}} {{wm attributes {id} -type tooltip}}; \
+                 afrish::nofocus {id}",
+                id = popup.id
+            )),
+            mock.last_command()
+        );
+
+        mock.clear();
+        popup.show_at(10, -20);
+        popup.show_near(10, 100, 120);
+        popup.hide();
+        popup.toplevel().background("ivory");
+        assert_eq!(
+            vec![
+                format!("afrish::popup {} 10 -20", popup.id),
+                format!("afrish::popup {} 10 120 100", popup.id),
+                format!("wm withdraw {}", popup.id),
+                format!("{} configure -background {{ivory}}", popup.id),
+            ],
+            mock.commands()
+        );
+    }
+}
@@ -6,6 +6,7 @@
 
 use super::grid;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -27,8 +28,13 @@ pub fn make_radio_button(
     value: &str,
 ) -> TkRadioButton {
     let id = wish::next_wid(parent.id());
-    let var = format!("::rb_group_{}", group);
-    let msg = format!("ttk::radiobutton {} -value {} -variable {}", id, value, var);
+    let var = tcl::quote(&format!("::rb_group_{}", group));
+    let msg = format!(
+        "ttk::radiobutton {} -value {} -variable {}",
+        id,
+        tcl::quote(value),
+        var
+    );
     wish::tell_wish(&msg);
 
     TkRadioButton { id, var }
@@ -47,9 +53,9 @@ impl widget::TkLabelOptions for TkRadioButton {}
 impl TkRadioButton {
     /// Sets the function to be called when the button is clicked.
     pub fn command(&self, command: impl Fn(bool) + Send + 'static) {
-        wish::add_callback1_bool(&self.id, wish::mk_callback1_bool(command));
+        wish::add_callback(&self.id, &self.id, command);
         let msg = format!(
-            "{} configure -command {{ puts cb1-{}-${} ; flush stdout }}",
+            "{} configure -command {{ afrish::event cb1-{}-[set {}] }}",
             self.id, self.id, self.var
         );
         wish::tell_wish(&msg);
@@ -68,12 +74,12 @@ impl TkRadioButton {
 
     /// Returns the selected value from this radio-button's group.
     pub fn value_get(&self) -> String {
-        let msg = format!("puts ${} ; flush stdout", self.var);
+        let msg = format!("set {}", self.var);
         wish::ask_wish(&msg)
     }
 
     pub fn value(&self, value: &str) {
-        let msg = format!("set {} {}", self.var, value);
+        let msg = format!("set {} {}", self.var, tcl::quote(value));
         wish::tell_wish(&msg);
     }
 }
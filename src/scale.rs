@@ -35,14 +35,17 @@ impl grid::TkGridLayout for TkScale {}
 impl pack::TkPackLayout for TkScale {}
 
 impl TkScale {
-    /// Sets the function to be called whenever the scale value is changed.
-    pub fn command(&self, command: impl Fn(f64) + Send + 'static) {
-        wish::add_callback1_float(&self.id, wish::mk_callback1_float(command));
+    /// Sets the function to be called whenever the scale value is changed,
+    /// returning a handle to unregister it.
+    pub fn command(&self, command: impl Fn(f64) + Send + 'static) -> wish::CallbackHandle {
+        let handle = wish::add_callback(&self.id, &self.id, command);
         let msg = format!(
             "{} configure -command [list scale_value {}]",
             self.id, self.id
         );
         wish::tell_wish(&msg);
+
+        handle
     }
 
     /// Sets the minimum value for the scale.
@@ -57,13 +60,9 @@ impl TkScale {
 
     /// Retrieves the scale's value.
     pub fn value_get(&self) -> f64 {
-        let msg = format!("puts [{} get] ; flush stdout", self.id);
+        let msg = format!("{} get", self.id);
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<f64>() {
-            value
-        } else {
-            0.0
-        }
+        result.parse::<f64>().unwrap_or(0.0)
     }
 
     /// Set the scale's value.
@@ -6,6 +6,7 @@
 
 use super::grid;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -54,13 +55,9 @@ impl TkSpinboxRange {
 
     /// Retrieves the spinbox's value.
     pub fn value_get(&self) -> f64 {
-        let msg = format!("puts [{} get] ; flush stdout", self.id);
+        let msg = format!("{} get", self.id);
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<f64>() {
-            value
-        } else {
-            0.0
-        }
+        result.parse::<f64>().unwrap_or(0.0)
     }
 
     /// Set to true so spinbox 'wraps' around at top/bottom.
@@ -80,15 +77,7 @@ pub struct TkSpinboxValues {
 /// This spinbox is used to select between a given list of string values.
 pub fn make_spinbox_values(parent: &impl widget::TkWidget, values: &[&str]) -> TkSpinboxValues {
     let id = wish::next_wid(parent.id());
-    let mut values_str = String::new();
-    for value in values {
-        values_str.push('{');
-        values_str.push_str(value);
-        values_str.push('}');
-        values_str.push(' ');
-    }
-
-    let msg = format!("ttk::spinbox {} -values {{{}}} ", id, values_str);
+    let msg = format!("ttk::spinbox {} -values {}", id, tcl::quote_list(values));
     wish::tell_wish(&msg);
 
     TkSpinboxValues { id }
@@ -113,7 +102,7 @@ impl TkSpinboxValues {
 
     /// Retrieves the spinbox's value.
     pub fn value_get(&self) -> String {
-        let msg = format!("puts [{} get] ; flush stdout", self.id);
+        let msg = format!("{} get", self.id);
         wish::ask_wish(&msg)
     }
 
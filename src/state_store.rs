@@ -0,0 +1,481 @@
+//! Window state store - remembers the size and layout of windows between
+//! runs of a program.
+//!
+//! A store holds the geometry and state of top-level windows, the sash
+//! positions of paned windows, the selected tab of notebooks and the column
+//! widths of treeviews, each under a name chosen by the program. Tracking a
+//! widget restores any state saved for it, and the store saves the state of
+//! all tracked widgets to its file:
+//!
+//! ```ignore
+//! let store = afrish::WindowStateStore::open("settings.state").unwrap();
+//! let settings = afrish::make_toplevel(&root);
+//! ...
+//! store.track_window("settings", &settings);
+//! store.track_paned_window("settings.panes", &panes);
+//! store.track_notebook("settings.tabs", &tabs);
+//! store.save_on_close(&settings, afrish::ClosePolicy::Hide);
+//! ```
+//!
+//! The file holds one `name.property = value` line for each value, and
+//! keeps the values of widgets not tracked in the current run. Lines
+//! starting with `#` are comments.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path;
+use std::sync::{Arc, Mutex};
+
+use super::notebook;
+use super::paned_window;
+use super::tcl;
+use super::timer;
+use super::toplevel;
+use super::treeview;
+use super::widget;
+use super::wish;
+
+// Delay after a window stops moving before its geometry is recorded.
+const CONFIGURE_DELAY: u64 = 250;
+
+#[derive(Clone, Debug)]
+enum Tracked {
+    Window(String, toplevel::TkTopLevel),
+    PanedWindow(String, paned_window::TkPanedWindow),
+    Notebook(String, notebook::TkNotebook),
+    Treeview(String, treeview::TkTreeview),
+}
+
+#[derive(Debug)]
+struct Store {
+    path: path::PathBuf,
+    entries: BTreeMap<String, String>,
+    tracked: Vec<Tracked>,
+}
+
+/// Refers to a store of window states, kept in a file.
+#[derive(Clone, Debug)]
+pub struct WindowStateStore {
+    store: Arc<Mutex<Store>>,
+}
+
+impl WindowStateStore {
+    /// Opens the store kept in given file, reading any values saved in it:
+    /// a file which does not exist gives an empty store. Lines which are
+    /// not understood are ignored.
+    pub fn open(path: impl AsRef<path::Path>) -> io::Result<WindowStateStore> {
+        let path = path.as_ref().to_path_buf();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+
+        Ok(WindowStateStore {
+            store: Arc::new(Mutex::new(Store {
+                path,
+                entries: parse_entries(&text),
+                tracked: vec![],
+            })),
+        })
+    }
+
+    /// Returns the value saved for given key, e.g. "settings.geometry".
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.store
+            .lock()
+            .unwrap()
+            .entries
+            .get(&clean_key(key))
+            .cloned()
+    }
+
+    /// Sets the value for given key, e.g. to save other settings with
+    /// the window states.
+    ///
+    /// Keys and values are kept as they read back from the file: each
+    /// newline becomes a space, as does each `=` in a key, and surrounding
+    /// spaces and any `#` starting a key are removed. The same key is used
+    /// by [get](WindowStateStore::get), and an empty key is ignored.
+    pub fn set(&self, key: &str, value: &str) {
+        let key = clean_key(key);
+        if key.is_empty() {
+            return;
+        }
+        let value = value.replace('\n', " ").trim().to_string();
+        self.store.lock().unwrap().entries.insert(key, value);
+    }
+
+    /// Restores the saved geometry of the window, and maximises it if it
+    /// was maximised, then tracks its geometry and state: a window is not
+    /// restored iconified or withdrawn. The window is moved onto the
+    /// screen if needed, e.g. if it was saved on a monitor no longer
+    /// connected.
+    pub fn track_window(&self, name: &str, window: &toplevel::TkTopLevel) {
+        if let Some(geometry) = self.get(&format!("{}.geometry", name)) {
+            if let Ok(geometry) = geometry.parse::<toplevel::Geometry>() {
+                window.geometry(geometry);
+                window.clamp_to_screen();
+            }
+        }
+        if self.get(&format!("{}.state", name)).as_deref() == Some("zoomed") {
+            window.zoomed(true);
+        }
+
+        // -- record the geometry once a window stops moving or resizing
+        let store = self.clone();
+        let tracked = window.clone();
+        let record_name = String::from(name);
+        let record = timer::Debouncer::new(window, CONFIGURE_DELAY, move || {
+            store.record_window(&record_name, &tracked);
+        });
+        let id = window.id.clone();
+        widget::TkWidget::bind(window, "<Configure>", move |event| {
+            if event.widget == id {
+                record.trigger();
+            }
+        });
+
+        self.track(Tracked::Window(String::from(name), window.clone()));
+    }
+
+    /// Restores the saved sash positions of the paned window, once it is
+    /// shown, and tracks them.
+    pub fn track_paned_window(&self, name: &str, paned_window: &paned_window::TkPanedWindow) {
+        if let Some(positions) = self.get(&format!("{}.sashes", name)) {
+            let script = tcl::list_items(&positions)
+                .iter()
+                .enumerate()
+                .filter_map(|(index, position)| {
+                    let position = position.parse::<u64>().ok()?;
+                    Some(format!(
+                        "{} sashpos {} {}",
+                        paned_window.id, index, position
+                    ))
+                })
+                .collect::<Vec<String>>()
+                .join("; ");
+            let msg = format!(
+                "afrish::when_mapped {} {}",
+                paned_window.id,
+                tcl::quote(&script)
+            );
+            wish::tell_wish(&msg);
+        }
+
+        self.track(Tracked::PanedWindow(
+            String::from(name),
+            paned_window.clone(),
+        ));
+    }
+
+    /// Restores the saved tab of the notebook, and tracks it.
+    pub fn track_notebook(&self, name: &str, notebook: &notebook::TkNotebook) {
+        if let Some(tab) = self.get(&format!("{}.tab", name)) {
+            if let Ok(tab) = tab.parse::<u64>() {
+                notebook.select_tab(tab);
+            }
+        }
+
+        self.track(Tracked::Notebook(String::from(name), notebook.clone()));
+    }
+
+    /// Restores the saved column widths of the treeview, and tracks them.
+    pub fn track_treeview(&self, name: &str, treeview: &treeview::TkTreeview) {
+        if let Some(widths) = self.get(&format!("{}.columns", name)) {
+            for pair in tcl::list_items(&widths).chunks_exact(2) {
+                if let Ok(width) = pair[1].parse::<u64>() {
+                    treeview.column_width(&pair[0], width);
+                }
+            }
+        }
+
+        self.track(Tracked::Treeview(String::from(name), treeview.clone()));
+    }
+
+    /// Records the current state of all tracked widgets which still exist.
+    pub fn capture(&self) {
+        let tracked = self.store.lock().unwrap().tracked.clone();
+        for item in tracked {
+            match item {
+                Tracked::Window(name, window) => {
+                    if exists(&window.id) {
+                        self.record_window(&name, &window);
+                    }
+                }
+                Tracked::PanedWindow(name, paned_window) => {
+                    if exists(&paned_window.id) {
+                        let count = paned_window.panes().len().saturating_sub(1);
+                        let positions: Vec<String> = (0..count as u64)
+                            .map(|index| paned_window.sash_position_get(index).to_string())
+                            .collect();
+                        self.set(&format!("{}.sashes", name), &tcl::join_list(&positions));
+                    }
+                }
+                Tracked::Notebook(name, notebook) => {
+                    if let Some(tab) = notebook.selected_tab() {
+                        self.set(&format!("{}.tab", name), &tab.to_string());
+                    }
+                }
+                Tracked::Treeview(name, treeview) => {
+                    if exists(&treeview.id) {
+                        let mut widths = vec![];
+                        let mut columns = vec![String::from("#0")];
+                        columns.extend(treeview.columns_get());
+                        for column in columns {
+                            let width = treeview.column_width_get(&column);
+                            widths.push(column);
+                            widths.push(width.to_string());
+                        }
+                        self.set(&format!("{}.columns", name), &tcl::join_list(&widths));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records the current state of all tracked widgets, and writes the
+    /// store to its file.
+    pub fn save(&self) -> io::Result<()> {
+        self.capture();
+        let store = self.store.lock().unwrap();
+        let text: String = store
+            .entries
+            .iter()
+            .map(|(key, value)| format!("{} = {}\n", key, value))
+            .collect();
+        fs::write(&store.path, text)
+    }
+
+    /// Saves the store when the user closes given window, then applies
+    /// given policy, returning a handle to unregister the command: errors
+    /// writing the file are ignored.
+    ///
+    /// This replaces any command set with [on_close](toplevel::TkTopLevel::on_close).
+    pub fn save_on_close(
+        &self,
+        window: &toplevel::TkTopLevel,
+        policy: toplevel::ClosePolicy,
+    ) -> wish::CallbackHandle {
+        let store = self.clone();
+        let closing = window.clone();
+        window.on_close(move || {
+            let _ = store.save();
+            match policy {
+                toplevel::ClosePolicy::Exit => wish::quit(),
+                toplevel::ClosePolicy::Hide => closing.withdraw(),
+            }
+        })
+    }
+
+    fn track(&self, item: Tracked) {
+        self.store.lock().unwrap().tracked.push(item);
+    }
+
+    // Records the state of a window, and its geometry while in the normal
+    // state, so a maximised window keeps the geometry it is restored to.
+    fn record_window(&self, name: &str, window: &toplevel::TkTopLevel) {
+        let state = if window.is_zoomed() {
+            toplevel::WindowState::Zoomed
+        } else {
+            window.state_get()
+        };
+        if state == toplevel::WindowState::Normal {
+            self.set(
+                &format!("{}.geometry", name),
+                &window.geometry_get().to_string(),
+            );
+        }
+        self.set(&format!("{}.state", name), &state.to_string());
+    }
+}
+
+// Returns true if widget with given id exists.
+fn exists(id: &str) -> bool {
+    wish::ask_wish(&format!("winfo exists {}", id)) == "1"
+}
+
+// Returns the key as it reads back from a store file.
+fn clean_key(key: &str) -> String {
+    key.replace(['\n', '='], " ")
+        .trim()
+        .trim_start_matches('#')
+        .trim_start()
+        .to_string()
+}
+
+// Parses the lines of a store file into keys and values.
+fn parse_entries(text: &str) -> BTreeMap<String, String> {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (String::from(key.trim()), String::from(value.trim())))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+
+    #[test]
+    fn parse_store() {
+        let entries = parse_entries(
+            "# window states\nmain.geometry = 640x480+-1200+0\n\nmain.state=zoomed\nbad line\n = x\n",
+        );
+        assert_eq!(2, entries.len());
+        assert_eq!(
+            Some("640x480+-1200+0"),
+            entries.get("main.geometry").map(|v| v.as_str())
+        );
+        assert_eq!(
+            Some("zoomed"),
+            entries.get("main.state").map(|v| v.as_str())
+        );
+    }
+
+    #[test]
+    fn save_and_restore() {
+        let path = std::env::temp_dir().join(format!("afrish-state-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let root = session.root();
+        let tabs = notebook::make_notebook(&root);
+        let panes = paned_window::make_paned_window(&root, widget::Orientation::Horizontal);
+
+        // -- a new store restores nothing, and saves the tracked widgets
+        let store = WindowStateStore::open(&path).unwrap();
+        mock.clear();
+        store.track_window("main", &root);
+        store.track_notebook("main.tabs", &tabs);
+        store.track_paned_window("main.panes", &panes);
+        let commands = mock.commands();
+        assert_eq!(1, commands.len());
+        assert!(commands[0].starts_with("bind {.} {<Configure>} {+afrish::bound "));
+
+        mock.reply("winfo exists .", "1");
+        mock.reply(
+            "if {[tk windowingsystem] eq {x11}} {wm attributes . -zoomed} else {expr {[wm state .] eq {zoomed}}}",
+            "0",
+        );
+        mock.reply("wm state .", "normal");
+        mock.reply("wm geometry .", "640x480+-1200+0");
+        mock.reply(&format!("{} index current", tabs.id), "2");
+        mock.reply(&format!("winfo exists {}", panes.id), "1");
+        mock.reply(&format!("{} panes", panes.id), ".a .b .c");
+        mock.reply(&format!("{} sashpos 0", panes.id), "100");
+        mock.reply(&format!("{} sashpos 1", panes.id), "250");
+        store.save().unwrap();
+        assert_eq!(
+            "main.geometry = 640x480+-1200+0\nmain.panes.sashes = 100 250\nmain.state = normal\nmain.tabs.tab = 2\n",
+            fs::read_to_string(&path).unwrap()
+        );
+
+        // -- a store opened from the file restores the widgets, keeping the
+        // -- window on the screen
+        let store = WindowStateStore::open(&path).unwrap();
+        mock.clear();
+        store.track_window("main", &root);
+        let commands = mock.commands();
+        assert_eq!(
+            vec!["wm geometry . 640x480+-1200+0", "afrish::place . clamp"],
+            commands[..2]
+        );
+        mock.clear();
+        store.track_notebook("main.tabs", &tabs);
+        store.track_paned_window("main.panes", &panes);
+        assert_eq!(
+            vec![
+                format!("{} select 2", tabs.id),
+                format!(
+                    "afrish::when_mapped {} {{{} sashpos 0 100; {} sashpos 1 250}}",
+                    panes.id, panes.id, panes.id
+                ),
+            ],
+            mock.commands()
+        );
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn save_other_values() {
+        let path = std::env::temp_dir().join(format!("afrish-values-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+
+        // -- values read back as they were set, with the same keys
+        let store = WindowStateStore::open(&path).unwrap();
+        let values = [
+            (" a = b ", " x = y "),
+            ("# c", "z"),
+            ("d\ne", "1\n2"),
+            ("f", ""),
+        ];
+        for (key, value) in values {
+            store.set(key, value);
+        }
+        store.set(" = ", "ignored");
+        store.save().unwrap();
+        assert_eq!(
+            "a   b = x = y\nc = z\nd e = 1 2\nf = \n",
+            fs::read_to_string(&path).unwrap()
+        );
+        let restored = WindowStateStore::open(&path).unwrap();
+        for (key, _) in values {
+            assert_eq!(store.get(key), restored.get(key));
+        }
+        assert_eq!(Some(String::from("x = y")), restored.get(" a = b "));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn save_and_restore_treeview() {
+        let path = std::env::temp_dir().join(format!("afrish-columns-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let files = treeview::make_treeview(&session.root());
+
+        let store = WindowStateStore::open(&path).unwrap();
+        mock.clear();
+        store.track_treeview("main.files", &files);
+        assert!(mock.commands().is_empty());
+
+        mock.reply(&format!("winfo exists {}", files.id), "1");
+        mock.reply(
+            &format!("{} cget -columns", files.id),
+            "name {last modified}",
+        );
+        mock.reply(&format!("{} column {{#0}} -width", files.id), "20");
+        mock.reply(&format!("{} column {{name}} -width", files.id), "120");
+        mock.reply(
+            &format!("{} column {{last modified}} -width", files.id),
+            "80",
+        );
+        store.save().unwrap();
+        assert_eq!(
+            "main.files.columns = {#0} 20 name 120 {last modified} 80\n",
+            fs::read_to_string(&path).unwrap()
+        );
+
+        let store = WindowStateStore::open(&path).unwrap();
+        mock.clear();
+        store.track_treeview("main.files", &files);
+        assert_eq!(
+            vec![
+                format!("{} column {{#0}} -width 20", files.id),
+                format!("{} column {{name}} -width 120", files.id),
+                format!("{} column {{last modified}} -width 80", files.id),
+            ],
+            mock.commands()
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
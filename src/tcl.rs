@@ -0,0 +1,513 @@
+//! Tcl quoting - turning arbitrary strings into safe Tcl words.
+//!
+//! Every string given to a widget setter ends up inside a Tcl command sent
+//! to wish. These functions quote a string, or a list of strings, so that
+//! Tcl reads back exactly the original text, whatever characters it
+//! contains: braces, backslashes, brackets, `$`, quotes or any Unicode.
+//!
+//! ```
+//! assert_eq!("{hello world}", afrish::quote("hello world"));
+//! assert_eq!("a\\{\\ \\[b\\]", afrish::quote("a{ [b]"));
+//! assert_eq!("{{a b} c}", afrish::quote_list(&["a b", "c"]));
+//! ```
+//!
+//! Results from wish are often Tcl lists or dicts, which can be parsed
+//! with [TclList] and [TclDict]:
+//!
+//! ```
+//! let list: afrish::TclList = "a {b c} \"d e\" {}".parse().unwrap();
+//! assert_eq!(vec!["a", "b c", "d e", ""], list.items);
+//! ```
+//!
+//! Use these when writing your own commands for
+//! [tell_wish](crate::wish::tell_wish) or [ask_wish](crate::wish::ask_wish):
+//!
+//! ```ignore
+//! let msg = format!("{} insert end {}", text.id, afrish::quote(user_text));
+//! afrish::tell_wish(&msg);
+//! ```
+//!
+
+use std::fmt;
+use std::str;
+
+/// Quotes text as a single Tcl word, which evaluates to exactly the text.
+///
+/// Text is placed in braces when that is safe: otherwise, every special
+/// character is escaped with a backslash.
+pub fn quote(text: &str) -> String {
+    if can_brace(text) {
+        return format!("{{{}}}", text);
+    }
+
+    let mut result = String::with_capacity(text.len() + 8);
+    for c in text.chars() {
+        match c {
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            '\\' | '{' | '}' | '[' | ']' | '$' | '"' | ';' | ' ' => {
+                result.push('\\');
+                result.push(c);
+            }
+            c if c.is_control() => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    result.push_str(&format!("\\u{:04x}", unit));
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// Returns the Tcl list holding given items, as a string: each item is
+/// quoted as a list element, and the elements separated by spaces.
+///
+/// The result is the value of the list, not a word: use
+/// [quote_list] to place a list within a command.
+pub fn join_list<S: AsRef<str>>(items: &[S]) -> String {
+    items
+        .iter()
+        .map(|item| list_element(item.as_ref()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Quotes given items as a single Tcl word, which evaluates to a list
+/// holding exactly the items.
+pub fn quote_list<S: AsRef<str>>(items: &[S]) -> String {
+    // -- each element is either a plain word, braced text without
+    //    backslashes, or is fully escaped, so the list can always be braced
+    format!("{{{}}}", join_list(items))
+}
+
+/// A Tcl list: parsed from a list's string value, and displayed as a
+/// well-formed list.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TclList {
+    pub items: Vec<String>,
+}
+
+/// Error returned if a string is not a well-formed Tcl list.
+#[derive(Debug, PartialEq)]
+pub struct ParseTclListErr;
+
+impl str::FromStr for TclList {
+    type Err = ParseTclListErr;
+
+    /// Parses a list, following the Tcl rules for braces, quotes and
+    /// backslash substitution.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut items = vec![];
+
+        let mut i = 0;
+        loop {
+            while i < chars.len() && is_list_space(chars[i]) {
+                i += 1;
+            }
+            if i == chars.len() {
+                break;
+            }
+
+            let mut item = String::new();
+            match chars[i] {
+                '{' => {
+                    // -- braced: kept verbatim, up to the matching brace
+                    let mut depth = 1;
+                    i += 1;
+                    loop {
+                        let c = *chars.get(i).ok_or(ParseTclListErr)?;
+                        i += 1;
+                        match c {
+                            '{' => depth += 1,
+                            '}' if depth == 1 => break,
+                            '}' => depth -= 1,
+                            '\\' if i < chars.len() => {
+                                item.push(c);
+                                item.push(chars[i]);
+                                i += 1;
+                                continue;
+                            }
+                            _ => {}
+                        }
+                        item.push(c);
+                    }
+                }
+                '"' => {
+                    // -- quoted: substituted, up to the closing quote
+                    i += 1;
+                    loop {
+                        match *chars.get(i).ok_or(ParseTclListErr)? {
+                            '"' => {
+                                i += 1;
+                                break;
+                            }
+                            '\\' => i = backslash(&chars, i, &mut item),
+                            c => {
+                                item.push(c);
+                                i += 1;
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    // -- a plain word, up to the next space
+                    while i < chars.len() && !is_list_space(chars[i]) {
+                        if chars[i] == '\\' {
+                            i = backslash(&chars, i, &mut item);
+                        } else {
+                            item.push(chars[i]);
+                            i += 1;
+                        }
+                    }
+                }
+            }
+
+            // -- braced and quoted items must end at a space
+            if i < chars.len() && !is_list_space(chars[i]) {
+                return Err(ParseTclListErr);
+            }
+            items.push(item);
+        }
+
+        Ok(TclList { items })
+    }
+}
+
+impl fmt::Display for TclList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", join_list(&self.items))
+    }
+}
+
+impl From<Vec<String>> for TclList {
+    fn from(items: Vec<String>) -> Self {
+        TclList { items }
+    }
+}
+
+impl From<TclList> for Vec<String> {
+    fn from(list: TclList) -> Self {
+        list.items
+    }
+}
+
+/// A Tcl dict: a list of alternating keys and values, such as the
+/// `-option value` pairs returned by Tk, kept in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TclDict {
+    pub entries: Vec<(String, String)>,
+}
+
+/// Error returned if a string is not a well-formed Tcl dict.
+#[derive(Debug, PartialEq)]
+pub struct ParseTclDictErr;
+
+impl TclDict {
+    /// Returns the value for given key: as in Tcl, the last value wins
+    /// if a key is repeated.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Sets the value for given key, replacing any existing value.
+    pub fn insert(&mut self, key: &str, value: &str) {
+        match self.entries.iter_mut().find(|(name, _)| name == key) {
+            Some((_, old)) => *old = String::from(value),
+            None => self.entries.push((String::from(key), String::from(value))),
+        }
+    }
+}
+
+impl str::FromStr for TclDict {
+    type Err = ParseTclDictErr;
+
+    /// Parses a dict from its list representation, which must hold an
+    /// even number of elements.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let list = s.parse::<TclList>().map_err(|_| ParseTclDictErr)?;
+        if list.items.len() % 2 != 0 {
+            return Err(ParseTclDictErr);
+        }
+
+        let mut items = list.items.into_iter();
+        let mut entries = vec![];
+        while let (Some(key), Some(value)) = (items.next(), items.next()) {
+            entries.push((key, value));
+        }
+
+        Ok(TclDict { entries })
+    }
+}
+
+impl fmt::Display for TclDict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let items: Vec<&str> = self
+            .entries
+            .iter()
+            .flat_map(|(key, value)| [key.as_str(), value.as_str()])
+            .collect();
+        write!(f, "{}", join_list(&items))
+    }
+}
+
+// Returns the items of a list returned by wish, or no items if the text
+// is not a well-formed list.
+pub(super) fn list_items(text: &str) -> Vec<String> {
+    text.parse::<TclList>()
+        .map(|list| list.items)
+        .unwrap_or_default()
+}
+
+// Quotes text as a list element: plain words are left as they are.
+fn list_element(text: &str) -> String {
+    let plain = !text.is_empty()
+        && !text.starts_with('#')
+        && !text.chars().any(|c| {
+            c.is_whitespace()
+                || c.is_control()
+                || matches!(c, '{' | '}' | '[' | ']' | '$' | '"' | ';' | '\\')
+        });
+
+    if plain {
+        String::from(text)
+    } else {
+        quote(text)
+    }
+}
+
+fn is_list_space(c: char) -> bool {
+    matches!(c, ' ' | '\t' | '\n' | '\r' | '\u{b}' | '\u{c}')
+}
+
+// Substitutes the backslash sequence starting at chars[i] onto the end of
+// text, returning the index of the following character.
+fn backslash(chars: &[char], i: usize, text: &mut String) -> usize {
+    let Some(&c) = chars.get(i + 1) else {
+        text.push('\\');
+        return i + 1;
+    };
+
+    let mut next = i + 2;
+    let value = match c {
+        'a' => '\u{7}',
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'v' => '\u{b}',
+        'x' | 'u' | 'U' => {
+            let most = match c {
+                'x' => 2,
+                'u' => 4,
+                _ => 8,
+            };
+            let digits: String = chars[next..]
+                .iter()
+                .take(most)
+                .take_while(|d| d.is_ascii_hexdigit())
+                .collect();
+            if digits.is_empty() {
+                c
+            } else {
+                next += digits.len();
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .unwrap_or(char::REPLACEMENT_CHARACTER)
+            }
+        }
+        '0'..='7' => {
+            let digits: String = chars[i + 1..]
+                .iter()
+                .take(3)
+                .take_while(|d| matches!(d, '0'..='7'))
+                .collect();
+            next = i + 1 + digits.len();
+            char::from(u32::from_str_radix(&digits, 8).unwrap_or(0) as u8)
+        }
+        '\n' => {
+            // -- backslash-newline and following blanks become one space
+            while matches!(chars.get(next), Some(' ' | '\t')) {
+                next += 1;
+            }
+            ' '
+        }
+        _ => c,
+    };
+    text.push(value);
+
+    next
+}
+
+// Text can be braced if Tcl reads it back unchanged: braces must balance,
+// and there must be no backslash or control character other than newline
+// and tab, as wish reads its input with translation of line endings.
+fn can_brace(text: &str) -> bool {
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return false,
+            '}' => depth -= 1,
+            '\\' => return false,
+            '\n' | '\t' => {}
+            c if c.is_control() => return false,
+            _ => {}
+        }
+    }
+
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::tcl_session;
+    use proptest::prelude::*;
+
+    #[test]
+    fn quote_1() {
+        assert_eq!("{}", quote(""));
+        assert_eq!("{abc}", quote("abc"));
+        assert_eq!("{puts [.r1 get]; $x}", quote("puts [.r1 get]; $x"));
+        assert_eq!("{a {b} c}", quote("a {b} c"));
+        assert_eq!("{line 1\nline 2}", quote("line 1\nline 2"));
+    }
+
+    #[test]
+    fn quote_2() {
+        assert_eq!("\\{", quote("{"));
+        assert_eq!("\\}\\{", quote("}{"));
+        assert_eq!("a\\\\b", quote("a\\b"));
+        assert_eq!("a\\nb\\{\\$c", quote("a\nb{$c"));
+        assert_eq!("\\r\\u0000\\u001b", quote("\r\u{0}\u{1b}"));
+        assert_eq!("Ɛɔ́\\ \\{", quote("Ɛɔ́ {"));
+    }
+
+    #[test]
+    fn quote_list_1() {
+        assert_eq!("{}", quote_list::<&str>(&[]));
+        assert_eq!("{a {b c}}", quote_list(&["a", "b c"]));
+        assert_eq!("{{} \\{ a\\\\ {#c}}", quote_list(&["", "{", "a\\", "#c"]));
+        assert_eq!("a b", join_list(&[String::from("a"), String::from("b")]));
+    }
+
+    fn items(text: &str) -> Vec<String> {
+        text.parse::<TclList>().unwrap().items
+    }
+
+    #[test]
+    fn tcl_list_1() {
+        assert!(items("").is_empty());
+        assert_eq!(vec!["abc"], items("abc"));
+        assert_eq!(vec!["abc", "def"], items("  abc  def  "));
+        assert_eq!(vec!["abc def"], items("{abc def}"));
+        assert_eq!(
+            vec!["abc def", "xy_z", "another"],
+            items("{abc def} xy_z {another}")
+        );
+    }
+
+    #[test]
+    fn tcl_list_2() {
+        // -- nested braces, quotes, empty elements and backslashes
+        assert_eq!(vec!["a {b} c", "", "d"], items("{a {b} c} {} d"));
+        assert_eq!(vec!["x y", "z"], items("\"x y\" z"));
+        assert_eq!(vec!["a b", "{", "c\\d"], items("a\\ b \\{ c\\\\d"));
+        assert_eq!(
+            vec!["a\\ b", "\tx\u{1f600}"],
+            items("{a\\ b} \\t\\x78\\U1f600")
+        );
+        assert_eq!(vec!["a b"], items("a\\\n   b"));
+        assert_eq!(vec!["/home/{me}/a.txt"], items("{/home/{me}/a.txt}"));
+    }
+
+    #[test]
+    fn tcl_list_3() {
+        assert_eq!(Err(ParseTclListErr), "{abc".parse::<TclList>());
+        assert_eq!(Err(ParseTclListErr), "{a}b".parse::<TclList>());
+        assert_eq!(Err(ParseTclListErr), "\"a".parse::<TclList>());
+        assert_eq!(
+            "a {b c} {} \\{",
+            TclList::from(vec![
+                String::from("a"),
+                String::from("b c"),
+                String::new(),
+                String::from("{")
+            ])
+            .to_string()
+        );
+    }
+
+    #[test]
+    fn tcl_dict_1() {
+        let mut dict = "-family {DejaVu Sans} -size 12 -size 14"
+            .parse::<TclDict>()
+            .unwrap();
+        assert_eq!(Some("DejaVu Sans"), dict.get("-family"));
+        assert_eq!(Some("14"), dict.get("-size"));
+        assert_eq!(None, dict.get("-slant"));
+
+        dict.insert("-family", "Noto {Sans}");
+        dict.insert("-slant", "italic");
+        assert_eq!(
+            "-family {Noto {Sans}} -size 12 -size 14 -slant italic",
+            dict.to_string()
+        );
+        assert_eq!(Err(ParseTclDictErr), "a b c".parse::<TclDict>());
+    }
+
+    #[test]
+    fn empty_list_round_trip() {
+        // -- the case saved in proptest-regressions/tcl.txt
+        let items: Vec<String> = vec![];
+        let list = TclList::from(items.clone());
+        assert_eq!("", list.to_string());
+        assert_eq!(Ok(list.clone()), "".parse::<TclList>());
+
+        let session = tcl_session();
+        let result = session.try_ask_wish("list").unwrap();
+        assert_eq!(Ok(list), result.parse::<TclList>());
+        let script = format!("llength {}", quote_list(&items));
+        assert_eq!("0", session.try_ask_wish(&script).unwrap());
+    }
+
+    proptest! {
+        #[test]
+        fn tcl_list_round_trip(items in prop::collection::vec(any::<String>(), 0..5)) {
+            let list = TclList::from(items.clone());
+            prop_assert_eq!(Ok(list.clone()), list.to_string().parse::<TclList>());
+
+            // -- lists written by Tcl itself are parsed back to the items
+            let script = format!("list {}", items.iter().map(|item| quote(item)).collect::<Vec<_>>().join(" "));
+            let result = tcl_session().try_ask_wish(&script).ok();
+            prop_assert_eq!(Some(Ok(list)), result.map(|text| text.parse::<TclList>()));
+        }
+
+        #[test]
+        fn quote_round_trip(text in any::<String>()) {
+            let script = format!("set _ {}", quote(&text));
+            prop_assert_eq!(Some(text), tcl_session().try_ask_wish(&script).ok());
+        }
+
+        #[test]
+        fn quote_list_round_trip(items in prop::collection::vec(any::<String>(), 0..5)) {
+            let session = tcl_session();
+            let script = format!("llength {}", quote_list(&items));
+            prop_assert_eq!(Some(items.len().to_string()), session.try_ask_wish(&script).ok());
+            for (index, item) in items.iter().enumerate() {
+                let script = format!("lindex {} {}", quote_list(&items), index);
+                prop_assert_eq!(Some(item.clone()), session.try_ask_wish(&script).ok());
+            }
+        }
+    }
+}
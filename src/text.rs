@@ -2,9 +2,11 @@
 //!
 //! * also see the Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/text.htm)
 
+use super::event;
 use super::grid;
 use super::image;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -90,7 +92,7 @@ impl TkText {
         (to_line, to_character): (u64, u64),
     ) -> String {
         let msg = format!(
-            "puts [{} get {}.{} {}.{}] ; flush stdout",
+            "{} get {}.{} {}.{}",
             &self.id, from_line, from_character, to_line, to_character
         );
         wish::ask_wish(&msg)
@@ -98,10 +100,7 @@ impl TkText {
 
     /// Get a range of text from a position to end.
     pub fn get_to_end(&self, (from_line, from_character): (u64, u64)) -> String {
-        let msg = format!(
-            "puts [{} get {}.{} end] ; flush stdout",
-            &self.id, from_line, from_character
-        );
+        let msg = format!("{} get {}.{} end", &self.id, from_line, from_character);
         wish::ask_wish(&msg)
     }
 
@@ -112,26 +111,30 @@ impl TkText {
 
     /// Insert at given (line, character) position of text.
     pub fn insert(&self, (line, character): (u64, u64), text: &str) {
-        let msg = format!("{} insert {}.{} {{{}}}", &self.id, line, character, text);
+        let msg = format!(
+            "{} insert {}.{} {}",
+            &self.id,
+            line,
+            character,
+            tcl::quote(text)
+        );
         wish::tell_wish(&msg);
     }
 
     /// Inserts at end of text.
     pub fn insert_end(&self, text: &str) {
-        let msg = format!("{} insert end {{{}}}", &self.id, text);
+        let msg = format!("{} insert end {}", &self.id, tcl::quote(text));
         wish::tell_wish(&msg);
     }
 
     /// Inserts at end of text, with given tags.
     pub fn insert_end_with_tags(&self, text: &str, tags: &[&str]) {
-        let mut tags_str = String::new();
-        for tag in tags {
-            tags_str.push('{');
-            tags_str.push_str(tag);
-            tags_str.push('}');
-            tags_str.push(' ');
-        }
-        let msg = format!("{} insert end {{{}}} {{{}}}", &self.id, text, tags_str);
+        let msg = format!(
+            "{} insert end {} {}",
+            &self.id,
+            tcl::quote(text),
+            tcl::quote_list(tags)
+        );
         wish::tell_wish(&msg);
     }
 
@@ -159,53 +162,52 @@ impl TkText {
     /// Insert at given (line, character) position of text,
     /// with given tags.
     pub fn insert_with_tags(&self, (line, character): (u64, u64), text: &str, tags: &[&str]) {
-        let mut tags_str = String::new();
-        for tag in tags {
-            tags_str.push('{');
-            tags_str.push_str(tag);
-            tags_str.push('}');
-            tags_str.push(' ');
-        }
         let msg = format!(
-            "{} insert {}.{} {{{}}} {{{}}}",
-            self.id, line, character, text, tags_str
+            "{} insert {}.{} {} {}",
+            self.id,
+            line,
+            character,
+            tcl::quote(text),
+            tcl::quote_list(tags)
         );
         wish::tell_wish(&msg);
     }
 
     /// Sets named mark's gravity to left.
     pub fn mark_gravity_left(&self, mark: &str) {
-        let msg = format!("{} mark gravity {} left", &self.id, mark);
+        let msg = format!("{} mark gravity {} left", &self.id, tcl::quote(mark));
         wish::tell_wish(&msg);
     }
 
     /// Sets named mark's gravity to right.
     pub fn mark_gravity_right(&self, mark: &str) {
-        let msg = format!("{} mark gravity {} right", &self.id, mark);
+        let msg = format!("{} mark gravity {} right", &self.id, tcl::quote(mark));
         wish::tell_wish(&msg);
     }
 
     /// Returns a (line, character) tuple for the given mark's position.
     pub fn mark_index(&self, mark: &str) -> (u64, u64) {
-        let msg = format!("puts [{} index {}] ; flush stdout", &self.id, mark);
+        let msg = format!("{} index {}", &self.id, tcl::quote(mark));
         let result = wish::ask_wish(&msg);
-        let parts: Vec<&str> = result.split('.').collect();
-        if parts.len() == 2 {
-            let line = parts[0].parse::<u64>().unwrap_or(1);
-            let character = parts[1].parse::<u64>().unwrap_or(0);
+        string_index(&result).unwrap_or((1, 0))
+    }
 
-            (line, character)
-        } else {
-            // TODO - can this fail?
-            (1, 0)
-        }
+    /// Returns a (line, character) tuple for the given mark's position,
+    /// or the error reported by Tk if the mark does not exist.
+    pub fn try_mark_index(&self, mark: &str) -> Result<(u64, u64), wish::TkError> {
+        let msg = format!("{} index {}", &self.id, tcl::quote(mark));
+        let result = wish::try_ask_wish(&msg)?;
+        string_index(&result).ok_or(wish::TkError::Parse {
+            expected: String::from("text index"),
+            response: result,
+        })
     }
 
     /// Returns a list of all the mark names defined in this text widget.
     pub fn mark_names(&self) -> Vec<String> {
-        let msg = format!("puts [{} mark names] ; flush stdout", &self.id);
+        let msg = format!("{} mark names", &self.id);
         let result = wish::ask_wish(&msg);
-        wish::split_items(&result)
+        tcl::list_items(&result)
     }
 
     /// Returns name of next mark from given position.
@@ -222,13 +224,19 @@ impl TkText {
 
     /// Sets named mark to given position.
     pub fn mark_set(&self, mark: &str, (line, character): (u64, u64)) {
-        let msg = format!("{} mark set {} {}.{}", &self.id, mark, line, character);
+        let msg = format!(
+            "{} mark set {} {}.{}",
+            &self.id,
+            tcl::quote(mark),
+            line,
+            character
+        );
         wish::tell_wish(&msg);
     }
 
     /// Removes named mark.
     pub fn mark_unset(&self, mark: &str) {
-        let msg = format!("{} mark unset {}", &self.id, mark);
+        let msg = format!("{} mark unset {}", &self.id, tcl::quote(mark));
         wish::tell_wish(&msg);
     }
 
@@ -255,8 +263,13 @@ impl TkText {
         text: &str,
     ) {
         let msg = format!(
-            "{} replace {}.{} {}.{} {{{}}}",
-            &self.id, from_line, from_character, to_line, to_character, text
+            "{} replace {}.{} {}.{} {}",
+            &self.id,
+            from_line,
+            from_character,
+            to_line,
+            to_character,
+            tcl::quote(text)
         );
         wish::tell_wish(&msg);
     }
@@ -266,19 +279,14 @@ impl TkText {
     /// position of the found text or none.
     pub fn search(&self, text: &str, (line, character): (u64, u64)) -> Option<(u64, u64)> {
         let msg = format!(
-            "puts [{} search {{{}}} {}.{}] ; flush stdout",
-            &self.id, text, line, character
+            "{} search -- {} {}.{}",
+            &self.id,
+            tcl::quote(text),
+            line,
+            character
         );
         let result = wish::ask_wish(&msg);
-        let parts: Vec<&str> = result.split('.').collect();
-        if parts.len() == 2 {
-            let line = parts[0].parse::<u64>().unwrap_or(1);
-            let character = parts[1].parse::<u64>().unwrap_or(0);
-
-            Some((line, character))
-        } else {
-            None
-        }
+        string_index(&result)
     }
 
     /// Arranges text widget display to ensure the given line, character
@@ -301,32 +309,37 @@ impl TkText {
         (to_line, to_character): (u64, u64),
     ) {
         let msg = format!(
-            "{} tag add {{{}}} {}.{} {}.{}",
-            &self.id, tag, from_line, from_character, to_line, to_character
+            "{} tag add {} {}.{} {}.{}",
+            &self.id,
+            tcl::quote(tag),
+            from_line,
+            from_character,
+            to_line,
+            to_character
         );
         wish::tell_wish(&msg);
     }
 
-    /// Binds event to given tag.
+    /// Binds event to given tag, in addition to any commands already
+    /// bound to it.
+    ///
+    /// # Panics
+    ///
+    /// If the pattern cannot be parsed.
     pub fn tag_bind(
         &self,
         tag: &str,
-        pattern: &str,
+        pattern: impl Into<event::EventPattern>,
         command: impl Fn(widget::TkEvent) + Send + 'static,
-    ) {
-        // id+tag+pattern used as identifier
-        let tag_pattern = format!("{}{}{}", &self.id, tag, pattern);
-        wish::add_callback1_event(&tag_pattern, wish::mk_callback1_event(command));
-        let msg = format!(
-            "{} tag bind {} {} {{ puts cb1e:{}:%x:%y:%X:%Y:%h:%w:%k:%K:%b ; flush stdout }}",
-            &self.id, tag, pattern, tag_pattern
-        );
-        wish::tell_wish(&msg);
+    ) -> widget::BindingHandle {
+        let bind_command = format!("{} tag bind {}", &self.id, tcl::quote(tag));
+        let name = format!("{}{}", &self.id, tag);
+        widget::bind_event(&bind_command, &self.id, &name, &pattern.into(), command)
     }
 
     /// Deletes a tag.
     pub fn tag_delete(&self, tag: &str) {
-        let msg = format!("{} tag delete {{{}}}", &self.id, tag);
+        let msg = format!("{} tag delete {}", &self.id, tcl::quote(tag));
         wish::tell_wish(&msg);
     }
 
@@ -335,26 +348,47 @@ impl TkText {
     /// For the available options, see the Tk
     /// [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/text.htm#M43)
     pub fn tag_configure(&self, tag: &str, option: &str, value: &str) {
-        let msg = format!("{} tag configure {} -{} {}", &self.id, tag, option, value);
+        let msg = format!(
+            "{} tag configure {} -{} {}",
+            &self.id,
+            tcl::quote(tag),
+            option,
+            tcl::quote(value)
+        );
         wish::tell_wish(&msg);
     }
 
+    /// Applies formatting to tags, as [tag_configure](TkText::tag_configure),
+    /// but returns the error reported by Tk if the option or value is invalid.
+    pub fn try_tag_configure(
+        &self,
+        tag: &str,
+        option: &str,
+        value: &str,
+    ) -> Result<(), wish::TkError> {
+        let msg = format!(
+            "{} tag configure {} -{} {}",
+            &self.id,
+            tcl::quote(tag),
+            option,
+            tcl::quote(value)
+        );
+        wish::try_tell_wish(&msg)
+    }
+
     /// Returns a list of all the tag names defined in this text widget.
     pub fn tag_names(&self) -> Vec<String> {
-        let msg = format!("puts [{} tag names] ; flush stdout", &self.id);
+        let msg = format!("{} tag names", &self.id);
         let result = wish::ask_wish(&msg);
-        wish::split_items(&result)
+        tcl::list_items(&result)
     }
 
     /// Returns a list of all the tag names defined in this text widget
     /// at the given location.
     pub fn tag_names_at(&self, (line, character): (u64, u64)) -> Vec<String> {
-        let msg = format!(
-            "puts [{} tag names {}.{}] ; flush stdout",
-            &self.id, line, character
-        );
+        let msg = format!("{} tag names {}.{}", &self.id, line, character);
         let result = wish::ask_wish(&msg);
-        wish::split_items(&result)
+        tcl::list_items(&result)
     }
 
     /// De-associates given tag with text in specified range.
@@ -365,8 +399,13 @@ impl TkText {
         (to_line, to_character): (u64, u64),
     ) {
         let msg = format!(
-            "{} tag remove {{{}}} {}.{} {}.{}",
-            &self.id, tag, from_line, from_character, to_line, to_character
+            "{} tag remove {} {}.{} {}.{}",
+            &self.id,
+            tcl::quote(tag),
+            from_line,
+            from_character,
+            to_line,
+            to_character
         );
         wish::tell_wish(&msg);
     }
@@ -381,3 +420,16 @@ impl TkText {
         widget::configure(&self.id, "wrap", &value.to_string());
     }
 }
+
+// Parse the line.character tcl string into a tuple: (line, character).
+fn string_index(text: &str) -> Option<(u64, u64)> {
+    let parts: Vec<&str> = text.split('.').collect();
+    if parts.len() == 2 {
+        let line = parts[0].parse::<u64>().unwrap_or(1);
+        let character = parts[1].parse::<u64>().unwrap_or(0);
+
+        Some((line, character))
+    } else {
+        None
+    }
+}
@@ -3,6 +3,7 @@
 //!
 //! * also see the Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/ttk_style.htm#M17)
 
+use super::tcl;
 use super::wish;
 
 /// Returns a list of the current themes.
@@ -28,18 +29,12 @@ use super::wish;
 /// ```
 ///
 pub fn theme_names() -> Vec<String> {
-    let themes = wish::ask_wish("puts [ttk::style theme names] ; flush stdout");
-
-    let mut result: Vec<String> = vec![];
-    for theme in themes.split_whitespace() {
-        result.push(String::from(theme));
-    }
-
-    result
+    let themes = wish::ask_wish("ttk::style theme names");
+    tcl::list_items(&themes)
 }
 
 /// Sets the current theme to the given theme-name
 pub fn use_theme(name: &str) {
-    let msg = format!("ttk::style theme use {}", name);
+    let msg = format!("ttk::style theme use {}", tcl::quote(name));
     wish::tell_wish(&msg);
 }
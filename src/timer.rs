@@ -0,0 +1,367 @@
+//! Timers - commands called after a delay, repeatedly, or when idle.
+//!
+//! Timers run in wish, which sends an event to the mainloop when each is
+//! due: their commands are called between other callbacks, so they may
+//! use widgets. Each function returns a [TimerHandle], which can cancel
+//! the timer before it is due:
+//!
+//! ```ignore
+//! let blink = afrish::every(&editor, 500, move || caret.toggle());
+//! ...
+//! blink.cancel();
+//! ```
+//!
+//! A [Debouncer] calls its command once a series of triggers has paused,
+//! e.g. to search after the user stops typing, and a [Throttle] calls its
+//! command at most once in each interval, however often it is triggered.
+//!
+//! Repeating timers, debouncers and throttles are owned by a widget: their
+//! commands are dropped when it is destroyed, if not cancelled before.
+//! One-shot timers drop their command once it is called.
+//!
+//! * also see the Tcl [manual](https://www.tcl-lang.org/man/tcl8.6/TclCmd/after.htm)
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::widget;
+use super::wish;
+
+/// Refers to a timer, returned when the timer is set.
+#[derive(Clone, Debug)]
+pub struct TimerHandle {
+    callback: wish::CallbackHandle,
+    cancel: String,
+}
+
+impl TimerHandle {
+    /// Cancels the timer, so its command is no longer called, and drops
+    /// the command.
+    pub fn cancel(&self) {
+        self.tell(&self.cancel);
+        self.callback.unregister();
+    }
+
+    /// Returns true if the timer's command may still be called: a one-shot
+    /// timer is finished once its command has been called.
+    pub fn is_pending(&self) -> bool {
+        self.callback.is_registered()
+    }
+
+    // Sends given command to the session the timer was set in, if it
+    // still exists.
+    fn tell(&self, msg: &str) {
+        if let Some(session) = self.callback.session() {
+            let _guard = session.enter();
+            wish::tell_wish(msg);
+        }
+    }
+}
+
+// Returns a name for a new timer, used as the key of its command.
+fn timer_name() -> String {
+    static NEXT_TIMER: AtomicU64 = AtomicU64::new(1);
+
+    format!("timer{}", NEXT_TIMER.fetch_add(1, Ordering::Relaxed))
+}
+
+// Sets a one-shot timer, using given `after` arguments for the script
+// sending its event: the same script cancels it.
+fn once(when: &str, command: impl Fn() + Send + 'static) -> TimerHandle {
+    let name = timer_name();
+    let callback = wish::add_callback_once(&name, move |()| command());
+    let script = format!("{{afrish::event clicked-{}}}", name);
+    wish::tell_wish(&format!("after {} {}", when, script));
+
+    TimerHandle {
+        callback,
+        cancel: format!("after cancel {}", script),
+    }
+}
+
+/// Calls given command once, after `time` milliseconds.
+pub fn after(time: u64, command: impl Fn() + Send + 'static) -> TimerHandle {
+    once(&time.to_string(), command)
+}
+
+/// Calls given command once, when wish next has no events to handle,
+/// e.g. after pending redraws.
+pub fn after_idle(command: impl Fn() + Send + 'static) -> TimerHandle {
+    once("idle", command)
+}
+
+/// Calls given command every `interval` milliseconds, until cancelled or
+/// the owner widget is destroyed.
+///
+/// The interval is timed by wish, from when each event is sent: a command
+/// taking longer than the interval is called again as soon as it returns.
+pub fn every(
+    owner: &impl widget::TkWidget,
+    interval: u64,
+    command: impl Fn() + Send + 'static,
+) -> TimerHandle {
+    let name = timer_name();
+    let callback = wish::add_callback(owner.id(), &name, move |()| command());
+    let script = format!("{{afrish::every {} {} {}}}", interval, name, owner.id());
+    wish::tell_wish(&format!("after {} {}", interval, script));
+
+    TimerHandle {
+        callback,
+        cancel: format!("after cancel {}", script),
+    }
+}
+
+/// Calls a command once triggering has paused for a delay: each trigger
+/// restarts the delay.
+///
+/// ```ignore
+/// let search = afrish::Debouncer::new(&query, 300, move || results.show(&query.value_get()));
+/// query.bind("<KeyRelease>", move |_| search.trigger());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Debouncer {
+    timer: TimerHandle,
+    restart: String,
+}
+
+impl Debouncer {
+    /// Creates a debouncer calling given command `delay` milliseconds after
+    /// the last trigger, until the owner widget is destroyed.
+    pub fn new(
+        owner: &impl widget::TkWidget,
+        delay: u64,
+        command: impl Fn() + Send + 'static,
+    ) -> Debouncer {
+        let name = timer_name();
+        let callback = wish::add_callback(owner.id(), &name, move |()| command());
+        let cancel = format!("after cancel {{afrish::event clicked-{}}}", name);
+        let restart = format!(
+            "{}; after {} {{afrish::event clicked-{}}}",
+            cancel, delay, name
+        );
+
+        Debouncer {
+            timer: TimerHandle { callback, cancel },
+            restart,
+        }
+    }
+
+    /// Starts, or restarts, the delay before the command is called.
+    pub fn trigger(&self) {
+        self.timer.tell(&self.restart);
+    }
+
+    /// Stops any pending call of the command, leaving the debouncer usable.
+    pub fn stop(&self) {
+        self.timer.tell(&self.timer.cancel);
+    }
+
+    /// Cancels any pending call, and drops the command: further triggers
+    /// are ignored.
+    pub fn cancel(&self) {
+        self.timer.cancel();
+    }
+}
+
+/// Calls a command at most once per interval, however often triggered.
+///
+/// The first trigger calls the command straight away; triggers during the
+/// following interval are collected into one call at its end.
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    timer: TimerHandle,
+    trigger: String,
+}
+
+impl Throttle {
+    /// Creates a throttle calling given command at most once every
+    /// `interval` milliseconds, until the owner widget is destroyed.
+    pub fn new(
+        owner: &impl widget::TkWidget,
+        interval: u64,
+        command: impl Fn() + Send + 'static,
+    ) -> Throttle {
+        let name = timer_name();
+        let callback = wish::add_callback(owner.id(), &name, move |()| command());
+        let cancel = format!(
+            "after cancel {{afrish::throttled {} {}}}; afrish::unthrottle {}",
+            name, interval, name
+        );
+
+        Throttle {
+            timer: TimerHandle { callback, cancel },
+            trigger: format!("afrish::throttle {} {}", name, interval),
+        }
+    }
+
+    /// Calls the command, now or at the end of the current interval.
+    pub fn trigger(&self) {
+        self.timer.tell(&self.trigger);
+    }
+
+    /// Cancels any pending call, and drops the command: further triggers
+    /// are ignored.
+    pub fn cancel(&self) {
+        self.timer.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{counter, MockWish};
+
+    // Returns the name of the timer in given command.
+    fn name_in(command: &str) -> String {
+        command
+            .split("clicked-")
+            .nth(1)
+            .unwrap()
+            .trim_end_matches([' ', '}'])
+            .to_string()
+    }
+
+    #[test]
+    fn timers() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let (count, command) = counter();
+        let timer = after(10, command);
+        let name = name_in(&mock.last_command().unwrap());
+        assert_eq!(
+            Some(format!("after 10 {{afrish::event clicked-{}}}", name)),
+            mock.last_command()
+        );
+
+        // -- cancelling tells wish and drops the command
+        timer.cancel();
+        assert_eq!(
+            Some(format!("after cancel {{afrish::event clicked-{}}}", name)),
+            mock.last_command()
+        );
+        assert!(!timer.is_pending());
+        mock.event(&format!("clicked-{}", name));
+        session.process_pending().unwrap();
+        assert_eq!(0, count.load(Ordering::SeqCst));
+
+        let (count, command) = counter();
+        let timer = after_idle(command);
+        let name = name_in(&mock.last_command().unwrap());
+        assert!(mock.last_command().unwrap().starts_with("after idle "));
+        mock.event(&format!("clicked-{}", name));
+        session.process_pending().unwrap();
+        assert_eq!(1, count.load(Ordering::SeqCst));
+        assert!(!timer.is_pending());
+    }
+
+    #[test]
+    fn repeating_timers() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let (count, command) = counter();
+        let timer = every(&session.root(), 500, command);
+        let name = mock
+            .last_command()
+            .unwrap()
+            .rsplit(' ')
+            .nth(1)
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            Some(format!("after 500 {{afrish::every 500 {} .}}", name)),
+            mock.last_command()
+        );
+
+        mock.event(&format!("clicked-{}", name));
+        mock.event(&format!("clicked-{}", name));
+        session.process_pending().unwrap();
+        assert_eq!(2, count.load(Ordering::SeqCst));
+        assert!(timer.is_pending());
+
+        timer.cancel();
+        assert_eq!(
+            Some(format!("after cancel {{afrish::every 500 {} .}}", name)),
+            mock.last_command()
+        );
+        assert!(!timer.is_pending());
+    }
+
+    #[test]
+    fn debounce_throttle() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let (count, command) = counter();
+        let debouncer = Debouncer::new(&session.root(), 300, command);
+
+        debouncer.trigger();
+        let name = name_in(mock.last_command().unwrap().rsplit(';').next().unwrap());
+        assert_eq!(
+            Some(format!(
+                "after cancel {{afrish::event clicked-{}}}; after 300 {{afrish::event clicked-{}}}",
+                name, name
+            )),
+            mock.last_command()
+        );
+        // -- the command stays registered after being called
+        mock.event(&format!("clicked-{}", name));
+        session.process_pending().unwrap();
+        debouncer.trigger();
+        mock.event(&format!("clicked-{}", name));
+        session.process_pending().unwrap();
+        assert_eq!(2, count.load(Ordering::SeqCst));
+
+        let throttle = Throttle::new(&session.root(), 100, || {});
+        throttle.trigger();
+        let name = mock
+            .last_command()
+            .unwrap()
+            .split(' ')
+            .nth(1)
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            Some(format!("afrish::throttle {} 100", name)),
+            mock.last_command()
+        );
+        throttle.cancel();
+        assert_eq!(
+            Some(format!(
+                "after cancel {{afrish::throttled {} 100}}; afrish::unthrottle {}",
+                name, name
+            )),
+            mock.last_command()
+        );
+    }
+
+    #[test]
+    fn owned_timers() {
+        // -- triggers go to the timer's own session, whichever is current
+        let mock = MockWish::new();
+        let session = mock.session();
+        let other = MockWish::new();
+        let other_session = other.session();
+        let guard = session.enter();
+        let button = crate::make_button(&session.root());
+        let debouncer = Debouncer::new(&button, 300, || {});
+        let throttle = Throttle::new(&button, 100, || {});
+        let timer = every(&button, 500, || {});
+        drop(guard);
+
+        let _guard = other_session.enter();
+        mock.clear();
+        debouncer.trigger();
+        debouncer.stop();
+        throttle.trigger();
+        assert_eq!(3, mock.commands().len());
+        assert!(other.commands().is_empty());
+
+        // -- destroying the owner drops the commands
+        mock.event(&format!("destroyed-{}", button.id));
+        session.process_pending().unwrap();
+        assert!(!debouncer.timer.is_pending());
+        assert!(!throttle.timer.is_pending());
+        assert!(!timer.is_pending());
+    }
+}
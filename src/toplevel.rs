@@ -2,10 +2,281 @@
 //!
 //! * also see the Tk [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/toplevel.htm)
 
+use std::fmt;
+use std::str;
+
+use super::image;
 use super::menu;
+use super::tcl;
 use super::widget;
 use super::wish;
 
+/// Defines what happens when the user closes a top-level window.
+///
+/// To call a command of your own instead, e.g. to ask whether to save
+/// changes, use [on_close](TkTopLevel::on_close): the command decides what
+/// happens, and can end with [quit](crate::wish::quit) or
+/// [withdraw](TkTopLevel::withdraw) to act like either policy. Setting a
+/// policy and setting a command each replace the other.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ClosePolicy {
+    /// Shuts down wish and returns from the mainloop, after running any
+    /// shutdown hooks: the default for the root window.
+    #[default]
+    Exit,
+    /// Hides (withdraws) the window, leaving the program running: the
+    /// window can be shown again with [deiconify](TkTopLevel::deiconify).
+    Hide,
+}
+
+impl ClosePolicy {
+    // Returns the Tcl command applying this policy to window with given id.
+    pub(super) fn protocol(&self, id: &str) -> String {
+        let command = match self {
+            ClosePolicy::Exit => String::from("afrish::event exit"),
+            ClosePolicy::Hide => format!("wm withdraw {}", id),
+        };
+        format!("wm protocol {} WM_DELETE_WINDOW {{ {} }}", id, command)
+    }
+}
+
+/// The state of a top-level window, as shown by the window manager.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowState {
+    /// Shown as a window.
+    Normal,
+    /// Iconified, e.g. minimised to a task bar.
+    Iconic,
+    /// Not shown, nor represented by an icon.
+    Withdrawn,
+    /// Used as the icon of another window.
+    Icon,
+    /// Maximised, on Windows and macOS: on X11, see
+    /// [zoomed](TkTopLevel::zoomed).
+    Zoomed,
+}
+
+impl fmt::Display for WindowState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            WindowState::Normal => "normal",
+            WindowState::Iconic => "iconic",
+            WindowState::Withdrawn => "withdrawn",
+            WindowState::Icon => "icon",
+            WindowState::Zoomed => "zoomed",
+        };
+        write!(f, "{}", &value)
+    }
+}
+
+/// Error returned if a window state is not recognised.
+#[derive(Debug)]
+pub struct ParseWindowStateErr;
+
+impl str::FromStr for WindowState {
+    type Err = ParseWindowStateErr;
+
+    /// Parse a window state, as returned by `wm state`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(WindowState::Normal),
+            "iconic" => Ok(WindowState::Iconic),
+            "withdrawn" => Ok(WindowState::Withdrawn),
+            "icon" => Ok(WindowState::Icon),
+            "zoomed" => Ok(WindowState::Zoomed),
+            _ => Err(ParseWindowStateErr),
+        }
+    }
+}
+
+/// The offset of a window from one edge of the screen, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Offset {
+    /// From the left or top edge: negative values are beyond the edge,
+    /// e.g. on a monitor to the left of the main monitor.
+    Start(i64),
+    /// From the right or bottom edge, to the window's right or bottom edge:
+    /// a window cannot be placed beyond these edges this way.
+    End(u64),
+}
+
+impl Default for Offset {
+    fn default() -> Offset {
+        Offset::Start(0)
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Offset::Start(offset) => format!("+{}", offset),
+            Offset::End(offset) => format!("-{}", offset),
+        };
+        write!(f, "{}", &value)
+    }
+}
+
+/// The size and position of a top-level window, as used by `wm geometry`
+/// in the form `WIDTHxHEIGHT±X±Y`, e.g. `640x480+-1200+0`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Geometry {
+    pub width: u64,
+    pub height: u64,
+    pub x: Offset,
+    pub y: Offset,
+}
+
+impl Geometry {
+    /// Creates a geometry with given size, positioned from the top-left
+    /// corner of the screen.
+    pub fn new(width: u64, height: u64, x: i64, y: i64) -> Geometry {
+        Geometry {
+            width,
+            height,
+            x: Offset::Start(x),
+            y: Offset::Start(y),
+        }
+    }
+}
+
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}{}{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Error returned if a geometry is incorrectly formatted.
+#[derive(Debug, PartialEq)]
+pub struct ParseGeometryErr;
+
+impl str::FromStr for Geometry {
+    type Err = ParseGeometryErr;
+
+    /// Parse a geometry in the form `WIDTHxHEIGHT±X±Y`, as returned by
+    /// `wm geometry`: an offset of `+-N` is N pixels beyond the left or top
+    /// edge, and `-N` is from the right or bottom edge.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().trim_start_matches('=');
+        let (width, rest) = s.split_once('x').ok_or(ParseGeometryErr)?;
+        let start = rest.find(['+', '-']).ok_or(ParseGeometryErr)?;
+        let (height, offsets) = rest.split_at(start);
+        let (x, rest) = parse_offset(offsets)?;
+        let (y, rest) = parse_offset(rest)?;
+        if !rest.is_empty() {
+            return Err(ParseGeometryErr);
+        }
+
+        let number = |text: &str| text.parse::<u64>().map_err(|_| ParseGeometryErr);
+        Ok(Geometry {
+            width: number(width)?,
+            height: number(height)?,
+            x,
+            y,
+        })
+    }
+}
+
+// Parses an offset at the start of given text, returning the rest of the
+// text: "+N" or "+-N" from the start, or "-N" from the end.
+fn parse_offset(text: &str) -> Result<(Offset, &str), ParseGeometryErr> {
+    let (from_end, rest) = match text.strip_prefix('+') {
+        Some(rest) => (false, rest),
+        None => (true, text.strip_prefix('-').ok_or(ParseGeometryErr)?),
+    };
+    let (negative, digits) = match rest.strip_prefix('-') {
+        Some(digits) if !from_end => (true, digits),
+        _ => (false, rest),
+    };
+    let length = digits
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(digits.len());
+    let value = digits[..length]
+        .parse::<u64>()
+        .map_err(|_| ParseGeometryErr)?;
+
+    let offset = if from_end {
+        Offset::End(value)
+    } else {
+        let value = i64::try_from(value).map_err(|_| ParseGeometryErr)?;
+        Offset::Start(if negative { -value } else { value })
+    };
+    Ok((offset, &digits[length..]))
+}
+
+/// Who chose a window's position or size: window managers may place
+/// windows positioned by the program, but should leave those positioned by
+/// the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GeometrySource {
+    Program,
+    User,
+}
+
+impl fmt::Display for GeometrySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            GeometrySource::Program => "program",
+            GeometrySource::User => "user",
+        };
+        write!(f, "{}", &value)
+    }
+}
+
+/// The purpose of a window, used by X11 window managers to decide how to
+/// decorate and place it: see the EWMH
+/// [specification](https://specifications.freedesktop.org/wm-spec/latest/).
+///
+/// Other platforms ignore the window type.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WindowType {
+    Combo,
+    Desktop,
+    Dialog,
+    Dnd,
+    Dock,
+    DropdownMenu,
+    Menu,
+    #[default]
+    Normal,
+    Notification,
+    PopupMenu,
+    Splash,
+    Toolbar,
+    Tooltip,
+    Utility,
+}
+
+impl fmt::Display for WindowType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            WindowType::Combo => "combo",
+            WindowType::Desktop => "desktop",
+            WindowType::Dialog => "dialog",
+            WindowType::Dnd => "dnd",
+            WindowType::Dock => "dock",
+            WindowType::DropdownMenu => "dropdown_menu",
+            WindowType::Menu => "menu",
+            WindowType::Normal => "normal",
+            WindowType::Notification => "notification",
+            WindowType::PopupMenu => "popup_menu",
+            WindowType::Splash => "splash",
+            WindowType::Toolbar => "toolbar",
+            WindowType::Tooltip => "tooltip",
+            WindowType::Utility => "utility",
+        };
+        write!(f, "{}", &value)
+    }
+}
+
+// Returns the Tcl command setting the type of window with given id, on
+// X11 only: window managers read the type when the window is mapped.
+pub(super) fn window_type_command(id: &str, value: WindowType) -> String {
+    format!(
+        "if {{[tk windowingsystem] eq {{x11}}}} {{wm attributes {} -type {}}}",
+        id, value
+    )
+}
+
 /// Refers to a top-level widget (window)
 #[derive(Clone, Debug, PartialEq)]
 pub struct TkTopLevel {
@@ -29,6 +300,68 @@ impl widget::TkWidget for TkTopLevel {
 }
 
 impl TkTopLevel {
+    /// Sets the opacity of the window, from 0.0 (transparent) to 1.0
+    /// (opaque): on X11, this needs a compositing window manager.
+    pub fn alpha(&self, value: f64) {
+        let msg = format!("wm attributes {} -alpha {}", self.id, value.clamp(0.0, 1.0));
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns the opacity of the window.
+    pub fn alpha_get(&self) -> f64 {
+        let msg = format!("wm attributes {} -alpha", self.id);
+        wish::ask_wish(&msg).parse::<f64>().unwrap_or(1.0)
+    }
+
+    /// Constrains the ratio of width to height of the window to lie between
+    /// `min_numer/min_denom` and `max_numer/max_denom`.
+    pub fn aspect(&self, min_numer: u64, min_denom: u64, max_numer: u64, max_denom: u64) {
+        let msg = format!(
+            "wm aspect {} {} {} {} {}",
+            self.id, min_numer, min_denom, max_numer, max_denom
+        );
+        wish::tell_wish(&msg);
+    }
+
+    /// Removes any constraint on the ratio of width to height.
+    pub fn aspect_clear(&self) {
+        let msg = format!("wm aspect {} {{}} {{}} {{}} {{}}", self.id);
+        wish::tell_wish(&msg);
+    }
+
+    /// Centres the window over given widget, usually its parent, keeping
+    /// it on the screen as [clamp_to_screen](TkTopLevel::clamp_to_screen).
+    pub fn center_on(&self, widget: &impl widget::TkWidget) {
+        let msg = format!("afrish::place {} widget {}", self.id, widget.id());
+        wish::tell_wish(&msg);
+    }
+
+    /// Centres the window on the visible area of the screen: see
+    /// [clamp_to_screen](TkTopLevel::clamp_to_screen).
+    pub fn center_on_screen(&self) {
+        let msg = format!("afrish::place {} screen", self.id);
+        wish::tell_wish(&msg);
+    }
+
+    /// Moves the window, if needed, so all of it is on the visible area of
+    /// the screen.
+    ///
+    /// The visible area is that of the virtual root window, where the
+    /// window manager provides one, or else of the screen, as Tk reports
+    /// them. Where several monitors form one X screen, the area spans all
+    /// of them, so parts of it need not be on a monitor.
+    pub fn clamp_to_screen(&self) {
+        let msg = format!("afrish::place {} clamp", self.id);
+        wish::tell_wish(&msg);
+    }
+
+    /// Centres the window on the mouse pointer, keeping it on the screen as
+    /// [clamp_to_screen](TkTopLevel::clamp_to_screen).
+    pub fn place_near_pointer(&self) {
+        let msg = format!("afrish::place {} pointer", self.id);
+        wish::tell_wish(&msg);
+    }
+
     /// Specifies the background colour.
     ///
     /// Colours are specified as a string, by either:
@@ -50,39 +383,36 @@ impl TkTopLevel {
         wish::tell_wish(&msg);
     }
 
+    /// Returns the platform's identifier for the outermost frame of the
+    /// window, including any decorations added by the window manager, as a
+    /// hexadecimal string.
+    pub fn frame(&self) -> String {
+        let msg = format!("wm frame {}", self.id);
+        wish::ask_wish(&msg)
+    }
+
     /// Expand window to occupy the full screen.
     pub fn full_screen(&self) {
         let msg = format!("wm attributes {} -fullscreen 1", self.id);
         wish::tell_wish(&msg);
     }
 
-    /// Retrieves the geometry of the window as a tuple: (width, height, x, y).
-    pub fn geometry_get(&self) -> (u64, u64, u64, u64) {
-        let msg = format!("puts [wm geometry {}] ; flush stdout", self.id);
-        let result = wish::ask_wish(&msg);
+    /// Retrieves the size and position of the window.
+    pub fn geometry_get(&self) -> Geometry {
+        let msg = format!("wm geometry {}", self.id);
+        wish::ask_wish(&msg).parse().unwrap_or_default()
+    }
 
-        string_geometry(&result)
+    /// Sets the size and position of a top-level window: see [Geometry].
+    pub fn geometry(&self, value: Geometry) {
+        let msg = format!("wm geometry {} {}", self.id, value);
+        wish::tell_wish(&msg);
     }
 
-    /// Sets the size and position of a top-level window.
-    ///
-    /// * `height` - the vertical height of the window, in pixels
-    /// * `width` - the horizontal width of the window, in pixels
-    /// * `x` - a positive value gives position relative to _left_ edge of screen,
-    ///         and a negative value gives position relative to _right_ edge.
-    /// * `y` - a positive value gives position relative to _top_ edge of screen,
-    ///         and a negative value gives position relative to _bottom_ edge.
-    pub fn geometry(&self, width: u64, height: u64, x: i64, y: i64) {
-        let msg = format!(
-            "wm geometry {} {}x{}{}{}{}{}",
-            self.id,
-            width,
-            height,
-            if x < 0 { "-" } else { "+" },
-            x.abs(),
-            if y < 0 { "-" } else { "+" },
-            y.abs()
-        );
+    /// Makes the window part of a group led by given window, e.g. so the
+    /// window manager can iconify the group together.
+    pub fn group(&self, leader: &impl widget::TkWidget) {
+        let msg = format!("wm group {} {}", self.id, leader.id());
         wish::tell_wish(&msg);
     }
 
@@ -97,6 +427,24 @@ impl TkTopLevel {
         wish::tell_wish(&msg);
     }
 
+    /// Sets the name shown with the window's icon, in place of its title.
+    pub fn icon_name(&self, name: &str) {
+        let msg = format!("wm iconname {} {}", self.id, tcl::quote(name));
+        wish::tell_wish(&msg);
+    }
+
+    /// Sets the image shown as the window's icon. If `default` is true, the
+    /// image is also used for top-level windows created later.
+    pub fn icon_photo(&self, image: &image::TkImage, default: bool) {
+        let msg = format!(
+            "wm iconphoto {} {}{}",
+            self.id,
+            if default { "-default " } else { "" },
+            image.id
+        );
+        wish::tell_wish(&msg);
+    }
+
     /// Sets the maximum width/height in pixels for the window.
     pub fn maximum_size(&self, width: u64, height: u64) {
         let msg = format!("wm maxsize {} {} {}", self.id, width, height);
@@ -114,14 +462,62 @@ impl TkTopLevel {
         wish::tell_wish(&msg);
     }
 
-    /// Call given command on closing the window.
-    pub fn on_close(&self, command: impl Fn() + Send + 'static) {
-        wish::add_callback0(&self.id, wish::mk_callback0(command));
+    /// Sets what happens when the user closes the window: see [ClosePolicy].
+    /// This replaces, and unregisters, any command set with
+    /// [on_close](TkTopLevel::on_close).
+    pub fn close_policy(&self, policy: ClosePolicy) {
+        wish::remove_command(&self.close_key());
+        wish::tell_wish(&policy.protocol(&self.id));
+    }
+
+    /// Call given command with the new state whenever the window's state
+    /// changes, e.g. when it is iconified or shown again, returning a handle
+    /// to unregister it. This replaces any command set by an earlier call.
+    pub fn on_state_change(
+        &self,
+        command: impl Fn(WindowState) + Send + 'static,
+    ) -> wish::CallbackHandle {
+        let key = format!("{}#state", self.id);
+        let id = self.id.clone();
+        let handle = wish::add_callback(&self.id, &key, move |()| {
+            let msg = format!("wm state {}", id);
+            if let Ok(state) = wish::ask_wish(&msg).parse() {
+                command(state);
+            }
+        });
+        // -- the script is removed before being added, so is bound once only
+        let script = format!("afrish::state_changed {} {} %W", key, self.id);
+        for pattern in ["<Map>", "<Unmap>", "<Configure>"] {
+            let msg = format!(
+                "afrish::unbind {{bind {id}}} {pattern} {{{script}}}; \
+                 bind {id} {pattern} {{+{script}}}",
+                id = self.id,
+            );
+            wish::tell_wish(&msg);
+        }
+
+        handle
+    }
+
+    /// Call given command on closing the window, in place of any
+    /// [ClosePolicy] or command set earlier, returning a handle to
+    /// unregister it.
+    pub fn on_close(&self, command: impl Fn() + Send + 'static) -> wish::CallbackHandle {
+        // -- registering under the window's close key replaces any earlier command
+        let key = self.close_key();
+        let handle = wish::add_callback(&self.id, &key, move |()| command());
         let msg = format!(
-            "wm protocol {} WM_DELETE_WINDOW {{ puts clicked-{} ; flush stdout }}",
-            self.id, self.id
+            "wm protocol {} WM_DELETE_WINDOW {{ afrish::event clicked-{} }}",
+            self.id, key
         );
         wish::tell_wish(&msg);
+
+        handle
+    }
+
+    // Returns the key of the command called on closing the window.
+    fn close_key(&self) -> String {
+        format!("{}#close", self.id)
     }
 
     /// Amount of horizontal padding for widget.
@@ -134,6 +530,12 @@ impl TkTopLevel {
         widget::configure(&self.id, "pady", &value.to_string());
     }
 
+    /// Sets who chose the window's position: by default, the program.
+    pub fn position_from(&self, value: GeometrySource) {
+        let msg = format!("wm positionfrom {} {}", self.id, value);
+        wish::tell_wish(&msg);
+    }
+
     /// Style of border around label.
     pub fn relief(&self, value: widget::Relief) {
         widget::configure(&self.id, "relief", &value.to_string());
@@ -150,9 +552,47 @@ impl TkTopLevel {
         wish::tell_wish(&msg);
     }
 
+    /// Sets who chose the window's size: by default, the program.
+    pub fn size_from(&self, value: GeometrySource) {
+        let msg = format!("wm sizefrom {} {}", self.id, value);
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns the ids of this window and its top-level descendants which
+    /// are shown, from the lowest in the stacking order to the highest.
+    pub fn stack_order(&self) -> Vec<String> {
+        let msg = format!("wm stackorder {}", self.id);
+        tcl::list_items(&wish::ask_wish(&msg))
+    }
+
+    /// Sets the state of the window: the `Icon` state cannot be set.
+    pub fn state(&self, value: WindowState) {
+        let msg = format!("wm state {} {}", self.id, value);
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns the state of the window.
+    pub fn state_get(&self) -> WindowState {
+        let msg = format!("wm state {}", self.id);
+        wish::ask_wish(&msg).parse().unwrap_or(WindowState::Normal)
+    }
+
     /// Sets the title text on a top-level window.
     pub fn title(&self, title: &str) {
-        let msg = format!("wm title {} {{{}}}\n", self.id, title);
+        let msg = format!("wm title {} {}", self.id, tcl::quote(title));
+        wish::tell_wish(&msg);
+    }
+
+    /// Marks the window as belonging to given window, e.g. a dialog, so the
+    /// window manager keeps it above and iconifies it with its master.
+    pub fn transient(&self, master: &impl widget::TkWidget) {
+        let msg = format!("wm transient {} {}", self.id, master.id());
+        wish::tell_wish(&msg);
+    }
+
+    /// Stops the window being transient for another window.
+    pub fn transient_clear(&self) {
+        let msg = format!("wm transient {} {{}}", self.id);
         wish::tell_wish(&msg);
     }
 
@@ -161,6 +601,12 @@ impl TkTopLevel {
         wish::tell_wish("update idletasks");
     }
 
+    /// Sets the type of the window, on X11: as window managers read the
+    /// type when a window is shown, set it while the window is withdrawn.
+    pub fn window_type(&self, value: WindowType) {
+        wish::tell_wish(&window_type_command(&self.id, value));
+    }
+
     /// Width of window, in columns.
     pub fn width(&self, width: u64) {
         widget::configure(&self.id, "width", &width.to_string());
@@ -172,6 +618,28 @@ impl TkTopLevel {
         wish::tell_wish(&msg);
     }
 
+    /// Maximises the window, or restores it to its previous size.
+    pub fn zoomed(&self, value: bool) {
+        let msg = format!(
+            "if {{[tk windowingsystem] eq {{x11}}}} {{wm attributes {id} -zoomed {}}} \
+             else {{wm state {id} {}}}",
+            if value { "1" } else { "0" },
+            if value { "zoomed" } else { "normal" },
+            id = self.id
+        );
+        wish::tell_wish(&msg);
+    }
+
+    /// Returns true if the window is maximised.
+    pub fn is_zoomed(&self) -> bool {
+        let msg = format!(
+            "if {{[tk windowingsystem] eq {{x11}}}} {{wm attributes {id} -zoomed}} \
+             else {{expr {{[wm state {id}] eq {{zoomed}}}}}}",
+            id = self.id
+        );
+        wish::ask_wish(&msg) == "1"
+    }
+
     /// Hide / Show the window border.
     pub fn border(&self, value: bool) {
         wish::tell_wish(&format!("wm overrideredirect {} {};", &self.id, !value));
@@ -182,42 +650,176 @@ impl TkTopLevel {
         wish::tell_wish(&format!("wm attributes {} -topmost {};", &self.id, value));
     }
 
-    /// Change the window position.
-    pub fn position(&self, x: u64, y: u64) {
+    /// Change the window position, relative to the top-left corner of the
+    /// screen: negative values are beyond the left or top edge.
+    pub fn position(&self, x: i64, y: i64) {
         wish::tell_wish(&format!("wm geometry {} +{}+{}", &self.id, x, y));
     }
 }
 
-// Parse the widthxheight+x+y tcl string into a tuple: (width, height, x, y).
-// -- we will ignore errors because this is only called on the return value
-// from a tcl call.
-// -- return (0,0,0,0) if there is a problem
-fn string_geometry(text: &str) -> (u64, u64, u64, u64) {
-    let parts: Vec<&str> = text.split(&['x', '+'][..]).collect();
-    let mut w = 0;
-    let mut h = 0;
-    let mut x = 0;
-    let mut y = 0;
-    if parts.len() == 4 {
-        w = parts[0].parse::<u64>().unwrap_or(0);
-        h = parts[1].parse::<u64>().unwrap_or(0);
-        x = parts[2].parse::<u64>().unwrap_or(0);
-        y = parts[3].parse::<u64>().unwrap_or(0);
-    }
-    (w, h, x, y)
-}
-
 #[cfg(test)]
 mod tests {
-    use super::string_geometry;
+    use super::*;
+    use crate::mock::{counter, MockWish};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn geometry() {
-        assert_eq!((0, 0, 0, 0), string_geometry("0x0+0+0"));
-        assert_eq!((10, 20, 100, 200), string_geometry("10x20+100+200"));
-        // - check some unlikely errors
-        assert_eq!((0, 0, 0, 0), string_geometry("0x00+0"));
-        assert_eq!((0, 0, 0, 0), string_geometry(""));
-        assert_eq!((0, 0, 0, 0), string_geometry("axbxcxd"));
+        assert_eq!(Ok(Geometry::new(0, 0, 0, 0)), "0x0+0+0".parse());
+        assert_eq!(Ok(Geometry::new(10, 20, 100, 200)), "10x20+100+200".parse());
+        // - offsets beyond the left or top edge, and from the right or bottom
+        assert_eq!(
+            Ok(Geometry::new(640, 480, -1200, 0)),
+            "640x480+-1200+0".parse()
+        );
+        assert_eq!(Ok(Geometry::new(1, 2, -3, -4)), "=1x2+-3+-4".parse());
+        assert_eq!(
+            Ok(Geometry {
+                width: 200,
+                height: 100,
+                x: Offset::End(0),
+                y: Offset::End(10)
+            }),
+            "200x100-0-10".parse()
+        );
+        // - check some errors
+        assert_eq!(Err(ParseGeometryErr), "0x00+0".parse::<Geometry>());
+        assert_eq!(Err(ParseGeometryErr), "".parse::<Geometry>());
+        assert_eq!(Err(ParseGeometryErr), "axbxcxd".parse::<Geometry>());
+        assert_eq!(Err(ParseGeometryErr), "1x2+3--4".parse::<Geometry>());
+        assert_eq!(Err(ParseGeometryErr), "1x2+3+4+5".parse::<Geometry>());
+
+        for text in ["640x480+-1200+0", "200x100-0-10", "1x2+3+4"] {
+            assert_eq!(text, text.parse::<Geometry>().unwrap().to_string());
+        }
+        for (x, y) in [
+            (Offset::End(5), Offset::Start(-7)),
+            (Offset::Start(-3), Offset::End(0)),
+            (Offset::Start(i64::MAX), Offset::End(u64::MAX)),
+        ] {
+            let geometry = Geometry {
+                width: 1,
+                height: 2,
+                x,
+                y,
+            };
+            assert_eq!(Ok(geometry), geometry.to_string().parse());
+        }
+    }
+
+    #[test]
+    fn window_manager() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let root = session.root();
+        let dialog = make_toplevel(&root);
+        mock.clear();
+
+        dialog.alpha(1.5);
+        dialog.transient(&root);
+        dialog.transient_clear();
+        dialog.position_from(GeometrySource::User);
+        dialog.aspect(1, 2, 3, 4);
+        dialog.state(WindowState::Iconic);
+        dialog.icon_photo(
+            &image::TkImage {
+                id: String::from("i1"),
+            },
+            true,
+        );
+        assert_eq!(
+            vec![
+                format!("wm attributes {} -alpha 1", dialog.id),
+                format!("wm transient {} .", dialog.id),
+                format!("wm transient {} {{}}", dialog.id),
+                format!("wm positionfrom {} user", dialog.id),
+                format!("wm aspect {} 1 2 3 4", dialog.id),
+                format!("wm state {} iconic", dialog.id),
+                format!("wm iconphoto {} -default i1", dialog.id),
+            ],
+            mock.commands()
+        );
+
+        mock.clear();
+        dialog.geometry(Geometry::new(300, 200, -1200, 10));
+        dialog.position(-5, 0);
+        dialog.center_on(&root);
+        assert_eq!(
+            vec![
+                format!("wm geometry {} 300x200+-1200+10", dialog.id),
+                format!("wm geometry {} +-5+0", dialog.id),
+                format!("afrish::place {} widget .", dialog.id),
+            ],
+            mock.commands()
+        );
+        mock.reply(&format!("wm geometry {}", dialog.id), "300x200+-1200+10");
+        assert_eq!(Geometry::new(300, 200, -1200, 10), dialog.geometry_get());
+
+        mock.reply(&format!("wm state {}", dialog.id), "withdrawn");
+        assert_eq!(WindowState::Withdrawn, dialog.state_get());
+        mock.reply("wm stackorder .", ". {.r1 a}");
+        assert_eq!(vec![".", ".r1 a"], root.stack_order());
+    }
+
+    #[test]
+    fn close_window() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let dialog = make_toplevel(&session.root());
+        let (count, command) = counter();
+        let first = dialog.on_close(|| {});
+        let second = dialog.on_close(command);
+        assert_eq!(
+            Some(format!(
+                "wm protocol {id} WM_DELETE_WINDOW {{ afrish::event clicked-{id}#close }}",
+                id = dialog.id
+            )),
+            mock.last_command()
+        );
+        assert!(!first.is_registered());
+        mock.event(&format!("clicked-{}#close", dialog.id));
+        session.process_pending().unwrap();
+        assert_eq!(1, count.load(Ordering::SeqCst));
+
+        // -- a policy replaces the command, and unregisters it
+        dialog.close_policy(ClosePolicy::Hide);
+        assert_eq!(
+            Some(format!(
+                "wm protocol {id} WM_DELETE_WINDOW {{ wm withdraw {id} }}",
+                id = dialog.id
+            )),
+            mock.last_command()
+        );
+        assert!(!second.is_registered());
+    }
+
+    #[test]
+    fn window_state_changes() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let root = session.root();
+        let states = Arc::new(Mutex::new(vec![]));
+        let states_clone = states.clone();
+        mock.clear();
+        let first = root.on_state_change(|_| {});
+        assert_eq!(
+            Some(String::from(
+                "afrish::unbind {bind .} <Configure> {afrish::state_changed .#state . %W}; \
+                 bind . <Configure> {+afrish::state_changed .#state . %W}"
+            )),
+            mock.last_command()
+        );
+
+        // -- a later command replaces the earlier one
+        root.on_state_change(move |state| states_clone.lock().unwrap().push(state));
+        assert!(!first.is_registered());
+        mock.reply("wm state .", "iconic");
+        mock.event("clicked-.#state");
+        session.process_pending().unwrap();
+        assert_eq!(vec![WindowState::Iconic], *states.lock().unwrap());
     }
 }
@@ -22,9 +22,11 @@
 //! * `<<TreeviewOpen>>` - when a node is opened
 //! * `<<TreeviewClose>>` - when a node is closed
 
+use super::event;
 use super::grid;
 use super::image;
 use super::pack;
+use super::tcl;
 use super::widget;
 use super::wish;
 
@@ -64,21 +66,29 @@ impl TkTreeview {
     /// Defines the names for the columns, used when referring to
     /// headings, columns or values.
     pub fn columns(&self, columns: &[&str]) {
-        let mut columns_str = String::new();
-        for column in columns {
-            columns_str.push('{');
-            columns_str.push_str(column);
-            columns_str.push('}');
-            columns_str.push(' ');
-        }
-
-        let msg = format!("{} configure -columns {{{}}}", &self.id, columns_str);
+        let msg = format!(
+            "{} configure -columns {}",
+            &self.id,
+            tcl::quote_list(columns)
+        );
         wish::tell_wish(&msg);
     }
 
+    /// Returns the names of the columns, as given to
+    /// [columns](TkTreeview::columns).
+    pub fn columns_get(&self) -> Vec<String> {
+        let msg = format!("{} cget -columns", &self.id);
+        tcl::list_items(&wish::ask_wish(&msg))
+    }
+
     /// Set the alignment for the given column.
     pub fn column_anchor(&self, column: &str, value: widget::Anchor) {
-        let msg = format!("{} column {} -anchor {}", &self.id, column, value);
+        let msg = format!(
+            "{} column {} -anchor {}",
+            &self.id,
+            tcl::quote(column),
+            value
+        );
         wish::tell_wish(&msg);
     }
 
@@ -87,7 +97,7 @@ impl TkTreeview {
         let msg = format!(
             "{} column {} -stretch {}",
             &self.id,
-            column,
+            tcl::quote(column),
             if value { "1" } else { "0" }
         );
         wish::tell_wish(&msg);
@@ -95,19 +105,36 @@ impl TkTreeview {
 
     /// Set the minimum-width in pixels for the given column.
     pub fn column_min_width(&self, column: &str, value: u64) {
-        let msg = format!("{} column {} -minwidth {}", &self.id, column, value);
+        let msg = format!(
+            "{} column {} -minwidth {}",
+            &self.id,
+            tcl::quote(column),
+            value
+        );
         wish::tell_wish(&msg);
     }
 
     /// Set the width in pixels for the given column.
     pub fn column_width(&self, column: &str, value: u64) {
-        let msg = format!("{} column {} -width {}", &self.id, column, value);
+        let msg = format!(
+            "{} column {} -width {}",
+            &self.id,
+            tcl::quote(column),
+            value
+        );
         wish::tell_wish(&msg);
     }
 
+    /// Returns the width in pixels of the given column: use "#0" for the
+    /// tree column.
+    pub fn column_width_get(&self, column: &str) -> u64 {
+        let msg = format!("{} column {} -width", &self.id, tcl::quote(column));
+        wish::ask_wish(&msg).parse::<u64>().unwrap_or(0)
+    }
+
     /// Returns the focussed item.
     pub fn focus(&self) -> TkTreeviewItem {
-        let msg = format!("puts [{} focus] ; flush stdout ", &self.id);
+        let msg = format!("{} focus", &self.id);
         let result = wish::ask_wish(&msg);
 
         TkTreeviewItem {
@@ -118,19 +145,34 @@ impl TkTreeview {
 
     /// Set the heading text for the given column.
     pub fn heading_text(&self, column: &str, title: &str) {
-        let msg = format!("{} heading {} -text {{{}}}", &self.id, column, title);
+        let msg = format!(
+            "{} heading {} -text {}",
+            &self.id,
+            tcl::quote(column),
+            tcl::quote(title)
+        );
         wish::tell_wish(&msg);
     }
 
     /// Set the heading image for the given column.
     pub fn heading_image(&self, column: &str, image: &image::TkImage) {
-        let msg = format!("{} heading {} -image {}", &self.id, column, &image.id);
+        let msg = format!(
+            "{} heading {} -image {}",
+            &self.id,
+            tcl::quote(column),
+            &image.id
+        );
         wish::tell_wish(&msg);
     }
 
     /// Set the heading alignment for the given column.
     pub fn heading_anchor(&self, column: &str, value: widget::Anchor) {
-        let msg = format!("{} heading {} -anchor {}", &self.id, column, value);
+        let msg = format!(
+            "{} heading {} -anchor {}",
+            &self.id,
+            tcl::quote(column),
+            value
+        );
         wish::tell_wish(&msg);
     }
 
@@ -141,7 +183,7 @@ impl TkTreeview {
 
     /// Create a new top-level item at end of treeview.
     pub fn insert_item(&self) -> TkTreeviewItem {
-        let msg = format!("puts [{} insert {{}} end] ; flush stdout", &self.id);
+        let msg = format!("{} insert {{}} end", &self.id);
         let result = wish::ask_wish(&msg);
 
         TkTreeviewItem {
@@ -152,7 +194,7 @@ impl TkTreeview {
 
     /// Create a new top-level item at given index position of treeview.
     pub fn insert_item_at(&self, index: u64) -> TkTreeviewItem {
-        let msg = format!("puts [{} insert {{}} {}] ; flush stdout", &self.id, index);
+        let msg = format!("{} insert {{}} {}", &self.id, index);
         let result = wish::ask_wish(&msg);
 
         TkTreeviewItem {
@@ -174,14 +216,14 @@ impl TkTreeview {
 
     /// Returns list of selected items.
     pub fn selected_items(&self) -> Vec<TkTreeviewItem> {
-        let query = format!("puts [{} selection] ; flush stdout", &self.id);
+        let query = format!("{} selection", &self.id);
         let values = wish::ask_wish(&query);
 
         let mut result: Vec<TkTreeviewItem> = vec![];
-        for value in values.split_whitespace() {
+        for value in tcl::list_items(&values) {
             result.push(TkTreeviewItem {
                 treeview: self.id.clone(),
-                id: String::from(value),
+                id: value,
             });
         }
 
@@ -203,21 +245,22 @@ impl TkTreeview {
         widget::configure(&self.id, "show", "tree");
     }
 
-    /// Binds event to given tag.
+    /// Binds event to given tag, in addition to any commands already
+    /// bound to it.
+    ///
+    /// # Panics
+    ///
+    /// If the pattern cannot be parsed.
     pub fn tag_bind(
         &self,
         tag: &str,
-        pattern: &str,
+        pattern: impl Into<event::EventPattern>,
         command: impl Fn(widget::TkEvent) + Send + 'static,
-    ) {
-        // id+tag+pattern used as identifier
-        let tag_pattern = format!("{}{}{}", &self.id, tag, pattern);
-        wish::add_callback1_event(&tag_pattern, wish::mk_callback1_event(command));
-        let msg = format!(
-            "{} tag bind {} {} {{ puts cb1e:{}:%x:%y:%X:%Y:%h:%w:%k:%K:%b ; flush stdout }}",
-            &self.id, tag, pattern, tag_pattern
-        );
-        wish::tell_wish(&msg);
+    ) -> widget::BindingHandle {
+        // ttk's tag bind does not accept the "+" form, so afrish::bind adds it
+        let bind_command = format!("afrish::bind {{{} tag bind {}}}", &self.id, tcl::quote(tag));
+        let name = format!("{}{}", &self.id, tag);
+        widget::bind_event(&bind_command, &self.id, &name, &pattern.into(), command)
     }
 
     /// Formatting is applied to tags using configuration options.
@@ -225,15 +268,39 @@ impl TkTreeview {
     /// For the available options, see the Tk
     /// [manual](https://www.tcl-lang.org/man/tcl8.6/TkCmd/ttk_treeview.htm#M72)
     pub fn tag_configure(&self, tag: &str, option: &str, value: &str) {
-        let msg = format!("{} tag configure {} -{} {}", &self.id, tag, option, value);
+        let msg = format!(
+            "{} tag configure {} -{} {}",
+            &self.id,
+            tcl::quote(tag),
+            option,
+            tcl::quote(value)
+        );
         wish::tell_wish(&msg);
     }
 
+    /// Applies formatting to tags, as [tag_configure](TkTreeview::tag_configure),
+    /// but returns the error reported by Tk if the option or value is invalid.
+    pub fn try_tag_configure(
+        &self,
+        tag: &str,
+        option: &str,
+        value: &str,
+    ) -> Result<(), wish::TkError> {
+        let msg = format!(
+            "{} tag configure {} -{} {}",
+            &self.id,
+            tcl::quote(tag),
+            option,
+            tcl::quote(value)
+        );
+        wish::try_tell_wish(&msg)
+    }
+
     /// Returns a list of all the tag names defined in this text widget.
     pub fn tag_names(&self) -> Vec<String> {
-        let msg = format!("puts [{} tag names] ; flush stdout", &self.id);
+        let msg = format!("{} tag names", &self.id);
         let result = wish::ask_wish(&msg);
-        wish::split_items(&result)
+        tcl::list_items(&result)
     }
 }
 
@@ -246,7 +313,12 @@ impl TkTreeviewItem {
 
     /// Sets the text label for the item.
     pub fn text(&self, value: &str) {
-        let msg = format!("{} item {} -text {{{}}}", &self.treeview, &self.id, value);
+        let msg = format!(
+            "{} item {} -text {}",
+            &self.treeview,
+            &self.id,
+            tcl::quote(value)
+        );
         wish::tell_wish(&msg);
     }
 
@@ -258,17 +330,11 @@ impl TkTreeviewItem {
 
     /// The list of values to display for this item.
     pub fn values(&self, values: &[&str]) {
-        let mut values_str = String::new();
-        for value in values {
-            values_str.push('{');
-            values_str.push_str(value);
-            values_str.push('}');
-            values_str.push(' ');
-        }
-
         let msg = format!(
-            "{} item {} -values {{{}}}",
-            &self.treeview, &self.id, values_str
+            "{} item {} -values {}",
+            &self.treeview,
+            &self.id,
+            tcl::quote_list(values)
         );
         wish::tell_wish(&msg);
     }
@@ -286,10 +352,7 @@ impl TkTreeviewItem {
 
     /// Returns true/false if item is open or closed.
     pub fn is_open(&self) -> bool {
-        let msg = format!(
-            "puts [{} item {} -open] ; flush stdout",
-            &self.treeview, &self.id
-        );
+        let msg = format!("{} item {} -open", &self.treeview, &self.id);
         let result = wish::ask_wish(&msg);
 
         result == "1"
@@ -297,10 +360,7 @@ impl TkTreeviewItem {
 
     /// Create a new item at end of this treeview item.
     pub fn insert_item(&self) -> TkTreeviewItem {
-        let msg = format!(
-            "puts [{} insert {} end] ; flush stdout ",
-            &self.treeview, &self.id
-        );
+        let msg = format!("{} insert {} end", &self.treeview, &self.id);
         let result = wish::ask_wish(&msg);
 
         TkTreeviewItem {
@@ -312,10 +372,7 @@ impl TkTreeviewItem {
     /// Create a new top-level item at given index position of this
     /// treeview item.
     pub fn insert_item_at(&self, index: u64) -> TkTreeviewItem {
-        let msg = format!(
-            "puts [{} insert {} {}] ; flush stdout",
-            &self.treeview, &self.id, index
-        );
+        let msg = format!("{} insert {} {}", &self.treeview, &self.id, index);
         let result = wish::ask_wish(&msg);
 
         TkTreeviewItem {
@@ -327,10 +384,7 @@ impl TkTreeviewItem {
     /// Returns an Option type containing the parent item if found, or
     /// None if this is a top-level item.
     pub fn parent(&self) -> Option<TkTreeviewItem> {
-        let msg = format!(
-            "puts [{} parent {}] ; flush stdout",
-            &self.treeview, &self.id
-        );
+        let msg = format!("{} parent {}", &self.treeview, &self.id);
         let result = wish::ask_wish(&msg);
 
         if result.is_empty() || result == "{}" {
@@ -347,7 +401,7 @@ impl TkTreeviewItem {
     /// this one in its list, if found, or None if this is the
     /// first child of its parent.
     pub fn previous(&self) -> Option<TkTreeviewItem> {
-        let msg = format!("puts [{} prev {}] ; flush stdout", &self.treeview, &self.id);
+        let msg = format!("{} prev {}", &self.treeview, &self.id);
         let result = wish::ask_wish(&msg);
 
         if result.is_empty() || result == "{}" {
@@ -364,7 +418,7 @@ impl TkTreeviewItem {
     /// this one in its list, if found, or None if this is the
     /// last child of its parent.
     pub fn next(&self) -> Option<TkTreeviewItem> {
-        let msg = format!("puts [{} next {}] ; flush stdout", &self.treeview, &self.id);
+        let msg = format!("{} next {}", &self.treeview, &self.id);
         let result = wish::ask_wish(&msg);
 
         if result.is_empty() || result == "{}" {
@@ -379,18 +433,15 @@ impl TkTreeviewItem {
 
     /// Returns a list of child items of given node.
     pub fn children(&self) -> Vec<TkTreeviewItem> {
-        let msg = format!(
-            "puts [{} children {}] ; flush stdout",
-            &self.treeview, &self.id
-        );
+        let msg = format!("{} children {}", &self.treeview, &self.id);
         let result = wish::ask_wish(&msg);
 
         let mut children: Vec<TkTreeviewItem> = vec![];
 
-        for child in result.split_whitespace() {
+        for child in tcl::list_items(&result) {
             children.push(TkTreeviewItem {
                 treeview: self.treeview.clone(),
-                id: String::from(child),
+                id: child,
             });
         }
 
@@ -399,15 +450,22 @@ impl TkTreeviewItem {
 
     /// Adds a tag to this item.
     pub fn tag_add(&self, tag: &str) {
-        let msg = format!("{} tag add {{{}}} {}", &self.treeview, tag, &self.id);
+        let msg = format!(
+            "{} tag add {} {}",
+            &self.treeview,
+            tcl::quote(tag),
+            &self.id
+        );
         wish::tell_wish(&msg);
     }
 
     /// Checks if this item has current tag.
     pub fn tag_has(&self, tag: &str) -> bool {
         let msg = format!(
-            "puts [{} tag has {{{}}} {}] ; flush stdout",
-            &self.treeview, tag, &self.id
+            "{} tag has {} {}",
+            &self.treeview,
+            tcl::quote(tag),
+            &self.id
         );
         let result = wish::ask_wish(&msg);
 
@@ -416,7 +474,111 @@ impl TkTreeviewItem {
 
     /// Removes a tag from this item.
     pub fn tag_remove(&self, tag: &str) {
-        let msg = format!("{} tag remove {{{}}} {}", &self.treeview, tag, &self.id);
+        let msg = format!(
+            "{} tag remove {} {}",
+            &self.treeview,
+            tcl::quote(tag),
+            &self.id
+        );
         wish::tell_wish(&msg);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockWish;
+
+    #[test]
+    fn columns_and_headings() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let treeview = make_treeview(&session.root());
+        assert_eq!(
+            Some(format!("ttk::treeview {}", treeview.id)),
+            mock.last_command()
+        );
+
+        mock.clear();
+        treeview.columns(&["name", "last modified"]);
+        treeview.heading_text("name", "Name");
+        treeview.column_width("name", 120);
+        treeview.column_stretch("name", false);
+        assert_eq!(
+            vec![
+                format!(
+                    "{} configure -columns {{name {{last modified}}}}",
+                    treeview.id
+                ),
+                format!("{} heading {{name}} -text {{Name}}", treeview.id),
+                format!("{} column {{name}} -width 120", treeview.id),
+                format!("{} column {{name}} -stretch 0", treeview.id),
+            ],
+            mock.commands()
+        );
+    }
+
+    #[test]
+    fn items() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let treeview = make_treeview(&session.root());
+
+        mock.reply(&format!("{} insert {{}} end", treeview.id), "I001");
+        mock.reply(&format!("{} insert I001 end", treeview.id), "I002");
+        let item = treeview.insert_item();
+        let child = item.insert_item();
+        assert_eq!(("I001", "I002"), (item.id.as_str(), child.id.as_str()));
+
+        mock.clear();
+        item.text("Documents");
+        child.values(&["a b", "c"]);
+        assert_eq!(
+            vec![
+                format!("{} item I001 -text {{Documents}}", treeview.id),
+                format!("{} item I002 -values {{{{a b}} c}}", treeview.id),
+            ],
+            mock.commands()
+        );
+
+        mock.reply(&format!("{} children I001", treeview.id), "I002 I003");
+        mock.reply(&format!("{} parent I001", treeview.id), "");
+        mock.reply(&format!("{} parent I002", treeview.id), "I001");
+        mock.reply(&format!("{} item I001 -open", treeview.id), "1");
+        let children: Vec<String> = item.children().into_iter().map(|c| c.id).collect();
+        assert_eq!(vec!["I002", "I003"], children);
+        assert_eq!(None, item.parent());
+        assert_eq!(Some(item.clone()), child.parent());
+        assert!(item.is_open());
+    }
+
+    #[test]
+    fn selection_and_tags() {
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let treeview = make_treeview(&session.root());
+
+        mock.reply(&format!("{} selection", treeview.id), "I004 I007");
+        let selected: Vec<String> = treeview
+            .selected_items()
+            .into_iter()
+            .map(|item| item.id)
+            .collect();
+        assert_eq!(vec!["I004", "I007"], selected);
+
+        mock.reply(&format!("{} tag names", treeview.id), "odd {even rows}");
+        assert_eq!(vec!["odd", "even rows"], treeview.tag_names());
+
+        treeview.tag_configure("odd", "background", "grey");
+        assert_eq!(
+            Some(format!(
+                "{} tag configure {{odd}} -background {{grey}}",
+                treeview.id
+            )),
+            mock.last_command()
+        );
+    }
+}
@@ -3,14 +3,20 @@
 //!
 
 use std::fmt;
+use std::ops;
+use std::sync::atomic::{AtomicU64, Ordering};
 
+use super::event;
 use super::font;
 use super::image;
+use super::tcl;
 use super::wish;
 
 /// Struct holding information from a bound event,
 /// returned as a parameter to the bound closure.
-#[derive(Clone, Debug)]
+///
+/// Fields which do not apply to the event are zero or empty.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct TkEvent {
     /// x-coordinate relative to current widget
     pub x: i64,
@@ -30,6 +36,180 @@ pub struct TkEvent {
     pub key_symbol: String,
     /// Number of mouse button in current event: 1 for left, 3 for right, etc.
     pub mouse_button: u64,
+    /// Text of the character typed, e.g. "é", or empty for keys such as
+    /// Shift which do not type a character.
+    pub unicode: String,
+    /// Modifier keys and mouse buttons held down during the event.
+    pub modifiers: Modifiers,
+    /// Distance the mouse wheel was turned, for a MouseWheel event.
+    pub delta: i64,
+    /// Path name of the widget receiving the event, e.g. ".r1".
+    pub widget: String,
+    /// Time of the event, in milliseconds from an arbitrary start.
+    pub time: u64,
+    /// Number representing the key symbol, e.g. 0x61 for "a".
+    pub keysym_number: u64,
+    /// Data of a virtual event, or the detail of an Enter, Leave, FocusIn
+    /// or FocusOut event, e.g. "NotifyAncestor".
+    pub data: String,
+    /// Type of the event, as numbered by X, e.g. 2 for KeyPress and 4 for
+    /// ButtonPress.
+    pub event_type: u64,
+}
+
+/// The modifier keys and mouse buttons held down during an event, as
+/// reported by Tk's `%s` field.
+///
+/// Modifiers are combined with `|`, and tested using
+/// [contains](Modifiers::contains):
+///
+/// ```
+/// use afrish::Modifiers;
+///
+/// let modifiers = Modifiers::SHIFT | Modifiers::CONTROL;
+/// assert!(modifiers.contains(Modifiers::CONTROL));
+/// assert!(!modifiers.contains(Modifiers::SHIFT | Modifiers::MOD1));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u32);
+
+impl Modifiers {
+    /// The Shift key.
+    pub const SHIFT: Modifiers = Modifiers(1);
+    /// Caps Lock.
+    pub const LOCK: Modifiers = Modifiers(1 << 1);
+    /// The Control key.
+    pub const CONTROL: Modifiers = Modifiers(1 << 2);
+    /// Usually the Alt key on X11, and the Command key on macOS.
+    pub const MOD1: Modifiers = Modifiers(1 << 3);
+    /// Usually Num Lock on X11, and the Option key on macOS.
+    pub const MOD2: Modifiers = Modifiers(1 << 4);
+    /// The third extra modifier, rarely used.
+    pub const MOD3: Modifiers = Modifiers(1 << 5);
+    /// Usually the Super (Windows) key on X11.
+    pub const MOD4: Modifiers = Modifiers(1 << 6);
+    /// Usually AltGr (ISO Level 3 Shift) on X11.
+    pub const MOD5: Modifiers = Modifiers(1 << 7);
+    /// Mouse button 1, usually the left button.
+    pub const BUTTON1: Modifiers = Modifiers(1 << 8);
+    /// Mouse button 2, usually the middle button.
+    pub const BUTTON2: Modifiers = Modifiers(1 << 9);
+    /// Mouse button 3, usually the right button.
+    pub const BUTTON3: Modifiers = Modifiers(1 << 10);
+    /// Mouse button 4.
+    pub const BUTTON4: Modifiers = Modifiers(1 << 11);
+    /// Mouse button 5.
+    pub const BUTTON5: Modifiers = Modifiers(1 << 12);
+    /// The Alt key on Windows.
+    pub const ALT: Modifiers = Modifiers(1 << 17);
+
+    /// Returns the modifiers with given bits, as reported by Tk.
+    pub fn from_bits(bits: u32) -> Modifiers {
+        Modifiers(bits)
+    }
+
+    /// Returns the bits of the modifiers.
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if no modifiers are held.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns true if all the given modifiers are held.
+    pub fn contains(&self, other: Modifiers) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl ops::BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 | other.0)
+    }
+}
+
+impl ops::BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, other: Modifiers) {
+        self.0 |= other.0;
+    }
+}
+
+impl ops::BitAnd for Modifiers {
+    type Output = Modifiers;
+
+    fn bitand(self, other: Modifiers) -> Modifiers {
+        Modifiers(self.0 & other.0)
+    }
+}
+
+/// Tells Tk whether to go on processing an event, once handled by a
+/// command bound with [bind_filter](TkWidget::bind_filter).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Propagation {
+    /// Go on to the other bindings for the event, e.g. of the widget's class.
+    #[default]
+    Continue,
+    /// Stop processing the event, so later bindings are not called: e.g.
+    /// an entry does not insert the typed character.
+    Break,
+}
+
+impl fmt::Display for Propagation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match self {
+            Propagation::Continue => "continue",
+            Propagation::Break => "break",
+        };
+        write!(f, "{}", &value)
+    }
+}
+
+/// Refers to a command bound to an event pattern: returned by the `bind`
+/// methods.
+///
+/// Binding a pattern adds to any commands already bound to it, and each
+/// command can be removed separately with [unbind](BindingHandle::unbind).
+#[derive(Clone, Debug)]
+pub struct BindingHandle {
+    callback: wish::CallbackHandle,
+    command: String,
+    pattern: String,
+    script: String,
+    key: String,
+}
+
+impl BindingHandle {
+    /// Removes the command, and its script from the Tk binding: other
+    /// commands bound to the same pattern are kept.
+    pub fn unbind(&self) {
+        if !self.callback.is_registered() {
+            return;
+        }
+        if let Some(session) = self.callback.session() {
+            session.tell_wish(&format!(
+                "afrish::unbind {} {} {}",
+                tcl::quote(&self.command),
+                tcl::quote(&self.pattern),
+                tcl::quote(&self.script)
+            ));
+        }
+        self.callback.unregister();
+    }
+
+    /// Returns true until the command is unbound, or its widget destroyed.
+    pub fn is_bound(&self) -> bool {
+        self.callback.is_registered()
+    }
+
+    /// Returns the name identifying this binding in
+    /// [WishEvent::Bound](wish::WishEvent::Bound) events.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
 }
 
 #[derive(Clone, Debug, Default)]
@@ -44,18 +224,21 @@ pub struct Style {
 
 impl Style {
     pub fn update(&self) {
+        let name = tcl::quote(self.name);
         wish::tell_wish(&format!(
-            "ttk::style layout {{{}}} [ttk::style layout {{{}}}];",
-            self.name, self.name
+            "ttk::style layout {} [ttk::style layout {}];",
+            name, name
         ));
         wish::tell_wish(&format!(
-            "ttk::style config {} -background {{{}}} -foreground {{{}}} -font {{{} {} {}}};",
-            self.name,
-            self.background,
-            self.foreground,
-            self.font_family,
-            self.font_size,
-            self.font_weight
+            "ttk::style config {} -background {} -foreground {} -font {};",
+            name,
+            tcl::quote(&self.background),
+            tcl::quote(&self.foreground),
+            tcl::quote_list(&[
+                self.font_family.clone(),
+                self.font_size.to_string(),
+                self.font_weight.clone()
+            ])
         ));
     }
 }
@@ -67,9 +250,81 @@ pub trait TkWidget {
     /// Returns the widget's id reference - used within tk
     fn id(&self) -> &str;
 
-    /// Binds a command to this widget to call on given event pattern
-    fn bind(&self, pattern: &str, command: impl Fn(TkEvent) + Send + 'static) {
-        bind_to(self.id(), pattern, command);
+    /// Binds a command to this widget to call on given event pattern,
+    /// in addition to any commands already bound to it.
+    ///
+    /// # Panics
+    ///
+    /// If the pattern cannot be parsed: see [try_bind](TkWidget::try_bind).
+    fn bind(
+        &self,
+        pattern: impl Into<event::EventPattern>,
+        command: impl Fn(TkEvent) + Send + 'static,
+    ) -> BindingHandle {
+        bind_to(self.id(), &pattern.into(), command)
+    }
+
+    /// Binds a command as [bind](TkWidget::bind) does, returning an error
+    /// if the pattern cannot be parsed, or if wish rejects the binding.
+    fn try_bind(
+        &self,
+        pattern: impl Into<event::EventPattern>,
+        command: impl Fn(TkEvent) + Send + 'static,
+    ) -> Result<BindingHandle, wish::TkError> {
+        let bind_command = format!("bind {}", tcl::quote(self.id()));
+        try_bind_event(
+            &bind_command,
+            self.id(),
+            self.id(),
+            &pattern.into(),
+            command,
+        )
+    }
+
+    /// Binds a command to this widget to call on given event pattern,
+    /// which decides whether Tk goes on to process the event, e.g. with
+    /// the bindings of the widget's class.
+    ///
+    /// Wish waits for the command to return, for up to half a second. If
+    /// the event occurs while wish is evaluating a command from rust, e.g.
+    /// during [update](wish::update), the command's result is ignored.
+    ///
+    /// # Panics
+    ///
+    /// If the pattern cannot be parsed: see
+    /// [try_bind_filter](TkWidget::try_bind_filter).
+    fn bind_filter(
+        &self,
+        pattern: impl Into<event::EventPattern>,
+        command: impl Fn(TkEvent) -> Propagation + Send + 'static,
+    ) -> BindingHandle {
+        let pattern = pattern.into();
+        if let Err(error) = pattern.check() {
+            panic!("{}", error);
+        }
+        let (handle, msg) = filter_binding(self.id(), &pattern, command);
+        wish::tell_wish(&msg);
+        handle
+    }
+
+    /// Binds a filter as [bind_filter](TkWidget::bind_filter) does,
+    /// returning an error if the pattern cannot be parsed, or if wish
+    /// rejects the binding.
+    fn try_bind_filter(
+        &self,
+        pattern: impl Into<event::EventPattern>,
+        command: impl Fn(TkEvent) -> Propagation + Send + 'static,
+    ) -> Result<BindingHandle, wish::TkError> {
+        let pattern = pattern.into();
+        pattern.check()?;
+        let (handle, msg) = filter_binding(self.id(), &pattern, command);
+        send_binding(handle, &msg)
+    }
+
+    /// Starts to generate an event on this widget, as if it came from the
+    /// window system: see [TkEventGenerator](event::TkEventGenerator).
+    fn generate_event(&self, pattern: impl Into<event::EventPattern>) -> event::TkEventGenerator {
+        event::TkEventGenerator::new(self.id(), pattern.into())
     }
 
     /// Retrieve the value of a configuration option
@@ -78,7 +333,7 @@ pub trait TkWidget {
     /// * `option` - the option to read
     ///
     fn cget(&self, option: &str) -> String {
-        let msg = format!("puts [{} cget -{}] ; flush stdout", self.id(), option);
+        let msg = format!("{} cget -{}", self.id(), option);
         wish::ask_wish(&msg)
     }
 
@@ -93,10 +348,23 @@ pub trait TkWidget {
         configure(self.id(), option, value);
     }
 
-    /// Destroys a widget and its children.
+    /// Retrieve the value of a configuration option as a string,
+    /// or the error reported by Tk, e.g. for an unknown option.
+    fn try_cget(&self, option: &str) -> Result<String, wish::TkError> {
+        let msg = format!("{} cget -{}", self.id(), option);
+        wish::try_ask_wish(&msg)
+    }
+
+    /// Used to change properties of a widget, as [configure](TkWidget::configure),
+    /// but returns the error reported by Tk if the option or value is
+    /// invalid, e.g. for an unknown colour name.
+    fn try_configure(&self, option: &str, value: &str) -> Result<(), wish::TkError> {
+        try_configure(self.id(), option, value)
+    }
+
+    /// Destroys a widget and its children, and removes their callbacks.
     fn destroy(&self) {
-        let msg = format!("destroy {}", self.id());
-        wish::tell_wish(&msg);
+        wish::destroy(self.id());
     }
 
     /// winfo retrieves information about widget.
@@ -106,6 +374,13 @@ pub trait TkWidget {
         wish::ask_wish(&msg)
     }
 
+    /// winfo retrieves information about widget, or the error
+    /// reported by Tk, e.g. for an unknown option.
+    fn try_winfo(&self, option: &str) -> Result<String, wish::TkError> {
+        let msg = format!("winfo {} {}", option, self.id());
+        wish::try_ask_wish(&msg)
+    }
+
     // -- TODO should be here, or more specific?
 
     /// Makes this widget the focus window (e.g. for key presses)
@@ -120,44 +395,28 @@ pub trait TkWidget {
     fn position_x(&self) -> u64 {
         let msg = format!("winfo x {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Returns the widget y position in pixels, within its parent.
     fn position_y(&self) -> u64 {
         let msg = format!("winfo y {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Returns the widget height in pixels.
     fn widget_height(&self) -> u64 {
         let msg = format!("winfo height {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Returns the widget width in pixels.
     fn widget_width(&self) -> u64 {
         let msg = format!("winfo width {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Returns the position of the mouse on screen of widget as (x,y).
@@ -169,66 +428,42 @@ pub trait TkWidget {
     fn mouse_x(&self) -> i64 {
         let msg = format!("winfo pointerx {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<i64>() {
-            value
-        } else {
-            -1
-        }
+        result.parse::<i64>().unwrap_or(-1)
     }
 
     /// Gives the y position of the mouse on screen of widget.
     fn mouse_y(&self) -> i64 {
         let msg = format!("winfo pointery {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<i64>() {
-            value
-        } else {
-            -1
-        }
+        result.parse::<i64>().unwrap_or(-1)
     }
 
     /// Height of screen of widget in pixels.
     fn screen_height(&self) -> u64 {
         let msg = format!("winfo screenheight {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Height of screen of widget in millimetres.
     fn screen_height_mm(&self) -> u64 {
         let msg = format!("winfo screenmmheight {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Width of screen of widget in pixels.
     fn screen_width(&self) -> u64 {
         let msg = format!("winfo screenwidth {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     /// Width of screen of widget in millimetres.
     fn screen_width_mm(&self) -> u64 {
         let msg = format!("winfo screenmmwidth {}", self.id());
         let result = wish::ask_wish(&msg);
-        if let Ok(value) = result.parse::<u64>() {
-            value
-        } else {
-            0
-        }
+        result.parse::<u64>().unwrap_or(0)
     }
 
     // -- stacking order
@@ -251,11 +486,11 @@ pub trait TkWidget {
     /// contained within this widget.
     fn grid_configure_column(&self, index: u64, option: &str, value: &str) {
         let msg = format!(
-            "grid columnconfigure {} {} -{} {{{}}}",
+            "grid columnconfigure {} {} -{} {}",
             self.id(),
             index,
             option,
-            value
+            tcl::quote(value)
         );
         wish::tell_wish(&msg);
     }
@@ -264,11 +499,11 @@ pub trait TkWidget {
     /// contained within this widget.
     fn grid_configure_row(&self, index: u64, option: &str, value: &str) {
         let msg = format!(
-            "grid rowconfigure {} {} -{} {{{}}}",
+            "grid rowconfigure {} {} -{} {}",
             self.id(),
             index,
             option,
-            value
+            tcl::quote(value)
         );
         wish::tell_wish(&msg);
     }
@@ -276,9 +511,9 @@ pub trait TkWidget {
     /// Apply a style to the widget.
     fn style(&self, style: &Style) {
         wish::tell_wish(&format!(
-            "{} configure -style {{{}}}",
+            "{} configure -style {}",
             self.id(),
-            style.name
+            tcl::quote(style.name)
         ));
     }
 }
@@ -336,7 +571,7 @@ pub trait TkLabelOptions: TkWidget {
 
     /// Sets the width of the widget, in characters
     fn width(&self, value: i64) {
-        let msg = format!("{} configure -width {{{}}}", self.id(), value);
+        let msg = format!("{} configure -width {}", self.id(), value);
         wish::tell_wish(&msg);
     }
 }
@@ -704,22 +939,153 @@ impl fmt::Display for Wrapping {
 // --------------------------------------------------------------------------
 // Internal functions for within crate use
 
-pub(super) fn bind_to(tag: &str, pattern: &str, command: impl Fn(TkEvent) + Send + 'static) {
-    // tag+pattern used as identifier, as multiple commands can be bound to each entity
-    let tag_pattern = format!("{}{}", tag, pattern); // TODO ? remove ':' ?
-    wish::add_callback1_event(&tag_pattern, wish::mk_callback1_event(command));
+pub(super) fn bind_to(
+    tag: &str,
+    pattern: &event::EventPattern,
+    command: impl Fn(TkEvent) + Send + 'static,
+) -> BindingHandle {
+    let bind_command = format!("bind {}", tcl::quote(tag));
+    bind_event(&bind_command, tag, tag, pattern, command)
+}
+
+/// Binds command to event pattern using given Tcl bind command, e.g.
+/// `bind .r1` or `.r1 tag bind x`, adding its script to any others bound
+/// to the pattern. The command is removed when the owner is destroyed.
+///
+/// Panics if the pattern cannot be parsed, before anything is sent.
+pub(super) fn bind_event(
+    bind_command: &str,
+    owner: &str,
+    name: &str,
+    pattern: &event::EventPattern,
+    command: impl Fn(TkEvent) + Send + 'static,
+) -> BindingHandle {
+    if let Err(error) = pattern.check() {
+        panic!("{}", error);
+    }
+    let (handle, msg) = add_binding(bind_command, owner, name, pattern, command);
+    wish::tell_wish(&msg);
+    handle
+}
+
+/// Binds command to event pattern as `bind_event` does, returning an
+/// error if the pattern cannot be parsed, or if wish rejects the binding.
+pub(super) fn try_bind_event(
+    bind_command: &str,
+    owner: &str,
+    name: &str,
+    pattern: &event::EventPattern,
+    command: impl Fn(TkEvent) + Send + 'static,
+) -> Result<BindingHandle, wish::TkError> {
+    pattern.check()?;
+    let (handle, msg) = add_binding(bind_command, owner, name, pattern, command);
+    send_binding(handle, &msg)
+}
+
+// Registers the command of a binding, returning its handle and the
+// message adding its script in wish.
+fn add_binding(
+    bind_command: &str,
+    owner: &str,
+    name: &str,
+    pattern: &event::EventPattern,
+    command: impl Fn(TkEvent) + Send + 'static,
+) -> (BindingHandle, String) {
+    let pattern = pattern.to_string();
+    let key = binding_key(name, &pattern);
+    let callback = wish::add_callback(owner, &key, command);
+    let script = format!("afrish::bound {} {}", event_key(&key), EVENT_FIELDS);
+    binding(bind_command, &pattern, callback, key, script)
+}
+
+// Registers the command of a filter bound to a widget, as for
+// add_binding.
+fn filter_binding(
+    widget: &str,
+    pattern: &event::EventPattern,
+    command: impl Fn(TkEvent) -> Propagation + Send + 'static,
+) -> (BindingHandle, String) {
+    let bind_command = format!("bind {}", tcl::quote(widget));
+    let pattern = pattern.to_string();
+    let key = binding_key(widget, &pattern);
+    let callback = wish::add_callback(widget, &key, move |filter: wish::FilterEvent| {
+        wish::decide(filter.request, command(filter.event));
+    });
+    let script = format!("afrish::filter {} {}", event_key(&key), EVENT_FIELDS);
+    binding(&bind_command, &pattern, callback, key, script)
+}
+
+// Sends the message adding a binding, waiting for wish to accept it: the
+// command is unregistered if wish rejects it.
+fn send_binding(handle: BindingHandle, msg: &str) -> Result<BindingHandle, wish::TkError> {
+    match wish::try_tell_wish(msg) {
+        Ok(()) => Ok(handle),
+        Err(error) => {
+            handle.callback.unregister();
+            Err(error)
+        }
+    }
+}
+
+// The event details sent to rust by a binding: Tk quotes each value as
+// it substitutes it, so each is passed as one word.
+const EVENT_FIELDS: &str = "%x %y %X %Y %h %w %k %K %b %A %s %D %W %t %N %d %T";
+
+// Returns the key as a word of a binding script, so it is not changed by
+// Tk's %-substitution.
+fn event_key(key: &str) -> String {
+    tcl::quote(key).replace('%', "%%")
+}
+
+// Returns a new key identifying a binding in event messages.
+fn binding_key(name: &str, pattern: &str) -> String {
+    static NEXT_BINDING: AtomicU64 = AtomicU64::new(1);
+
+    // name+pattern+number used as identifier, as multiple commands can be bound to each entity
+    format!(
+        "{}{}#{}",
+        name,
+        pattern,
+        NEXT_BINDING.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+// Returns the handle for a registered callback, and the message adding
+// its script to the binding for pattern.
+fn binding(
+    bind_command: &str,
+    pattern: &str,
+    callback: wish::CallbackHandle,
+    key: String,
+    script: String,
+) -> (BindingHandle, String) {
     let msg = format!(
-        "bind {} {} {{ puts cb1e:{}:%x:%y:%X:%Y:%h:%w:%k:%K:%b ; flush stdout }}",
-        tag, pattern, tag_pattern
+        "{} {} {}",
+        bind_command,
+        tcl::quote(pattern),
+        tcl::quote(&format!("+{}", script))
     );
-    wish::tell_wish(&msg);
+    let handle = BindingHandle {
+        callback,
+        command: String::from(bind_command),
+        pattern: String::from(pattern),
+        script,
+        key,
+    };
+
+    (handle, msg)
 }
 
 pub(super) fn configure(wid: &str, option: &str, value: &str) {
-    let msg = format!("{} configure -{} {{{}}}", wid, option, value);
+    let msg = format!("{} configure -{} {}", wid, option, tcl::quote(value));
     wish::tell_wish(&msg);
 }
 
+pub(super) fn try_configure(wid: &str, option: &str, value: &str) -> Result<(), wish::TkError> {
+    let msg = format!("{} configure -{} {}", wid, option, tcl::quote(value));
+    wish::try_tell_wish(&msg)
+}
+
 pub(super) fn padding(wid: &str, values: &[u64]) {
     let mut value_str = String::new();
     for i in values.iter() {
@@ -731,18 +1097,17 @@ pub(super) fn padding(wid: &str, values: &[u64]) {
 
 // --------------------------------------------------------------------------
 
-/// Triggers given command after 'time' milliseconds.
-pub fn after(time: u64, command: impl Fn() + Send + 'static) {
-    wish::next_wid(".");
-    let name = format!("after{}", wish::current_id());
-    wish::add_callback0(&name, wish::mk_callback0(command));
-    let msg = format!("after {} {{ puts clicked-{} ; flush stdout }}", time, name);
-    wish::tell_wish(&msg);
-}
-
-/// Binds command for event pattern to _all_ widgets.
-pub fn bind(pattern: &str, command: impl Fn(TkEvent) + Send + 'static) {
-    bind_to("all", pattern, command);
+/// Binds command for event pattern to _all_ widgets, in addition to any
+/// commands already bound to it.
+///
+/// # Panics
+///
+/// If the pattern cannot be parsed.
+pub fn bind(
+    pattern: impl Into<event::EventPattern>,
+    command: impl Fn(TkEvent) + Send + 'static,
+) -> BindingHandle {
+    bind_to("all", &pattern.into(), command)
 }
 
 /// Checks what the current OS system is: see
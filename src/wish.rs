@@ -8,7 +8,7 @@
 //!
 //!   // -- add code here to create program
 //!
-//!   afrish::mainloop();
+//!   afrish::mainloop().unwrap();
 //! }
 //! ```
 //!
@@ -37,8 +37,84 @@
 //! process and react to events: the call to `mainloop` is usually the last
 //! statement in the program.
 //!
+//! Alternatively, a program with its own loop can handle events as they
+//! arrive using [poll_event], or [update] to handle all pending events:
+//!
+//! ```ignore
+//! loop {
+//!   match afrish::poll_event(std::time::Duration::from_millis(10)) {
+//!     Ok(Some(afrish::WishEvent::Exit(_))) | Err(_) => break,
+//!     _ => {}
+//!   }
+//!   // -- check for other input here
+//! }
+//! ```
+//!
 //! The program will usually exit when the top-level window is closed. However,
-//! that can be over-ridden or, to exit in another way, use [end_wish].
+//! that can be over-ridden using [close_policy](toplevel::TkTopLevel::close_policy)
+//! or [on_close](toplevel::TkTopLevel::on_close). To exit in another way,
+//! use [quit], which makes `mainloop` return, or [end_wish], which ends
+//! the program immediately. Commands registered with [on_shutdown] are
+//! called before wish exits.
+//!
+//! `mainloop` returns an [ExitReason] once wish has finished, telling
+//! whether the window was closed, `end_wish` was called, or wish died, so a
+//! long-running program can decide to start a new wish process:
+//!
+//! ```ignore
+//! match afrish::mainloop() {
+//!   Ok(afrish::ExitReason::WishDied(status)) => println!("wish died: {}", status),
+//!   Ok(_) => {}
+//!   Err(error) => println!("{}", error),
+//! }
+//! ```
+//!
+//! ## Sessions
+//!
+//! `start_wish` starts a default [TkSession], which is used by the free
+//! functions in this crate. Further wish processes can be started with
+//! [TkSession::start_with]: widgets are created in a session by entering
+//! it on the current thread, and each session runs its own mainloop.
+//!
+//! ```ignore
+//! let session = afrish::TkSession::start_with("wish").unwrap();
+//! let root = session.root();
+//! let _guard = session.enter();
+//!
+//! let label = afrish::make_label(&root);
+//! label.text("Hello from a second wish");
+//! label.grid().layout();
+//!
+//! session.mainloop().unwrap();
+//! ```
+//!
+//! Widgets do not hold their session: their methods send commands to the
+//! current session, so a session must be entered while its widgets are
+//! used. In debug builds, sending a command naming a widget created in
+//! another session panics; the root window, `.`, is shared by all sessions
+//! and is not checked.
+//!
+//! Once a default session has ended, e.g. because wish has been closed,
+//! a new one may be started with `start_wish`.
+//!
+//! A session communicates with wish through a [Transport]: for testing,
+//! a session can instead use a [MockWish](crate::mock::MockWish), which
+//! records the commands sent to it.
+//!
+//! Other threads should not create or change widgets directly: instead,
+//! they can post closures to run on the mainloop through a [UiHandle].
+//!
+//! Callbacks are called on the thread running the mainloop: callbacks
+//! which are not `Send`, or which change the program's state, can be
+//! registered using the [local] module.
+//!
+//! ## Async
+//!
+//! With the "async" feature enabled, a program using async/await can read
+//! events from an [event_stream] in place of the mainloop, and query wish
+//! with [ask_wish_async], or dialogs' `show_async` methods, without blocking
+//! its executor. Callbacks registered on widgets are still called as each
+//! event is read from the stream.
 //!
 //! ## Low-level API
 //!
@@ -52,7 +128,7 @@
 //! 1. every widget has an `id` field, which gives the Tk identifier.
 //! 2. [tell_wish] sends a given string directly to wish
 //! 3. [ask_wish] sends a given string directly to wish and
-//!    returns, as a [String], the response.
+//!    returns, as a [String], the result of evaluating it.
 //!
 //! For example, label's
 //! [takefocus](https://www.tcl-lang.org/man/tcl8.6/TkCmd/ttk_widget.htm#M-takefocus)
@@ -64,6 +140,9 @@
 //! afrish::tell_wish(&format!("{} configure -takefocus 0", &label.id));
 //! ```
 //!
+//! Strings from users should be quoted with [quote](crate::tcl::quote) or
+//! [quote_list](crate::tcl::quote_list) before being placed in a command.
+//!
 //! Also useful are:
 //!
 //! * [cget](widget::TkWidget::cget) - queries any option and returns its current value
@@ -81,571 +160,2399 @@
 //! 4. wrapping the widget's functions as methods, calling out to Tk with
 //!    the stored id as a reference.
 //!
+//! Commands which need to notify rust of an event, such as a button's
+//! `-command`, should call `afrish::event` in Tcl with a single-word message,
+//! rather than writing to stdout directly: all output from wish is framed
+//! so that event messages and query results can be told apart.
+//!
 
-use std::collections::HashMap;
-use std::io::{Read, Write};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
 use std::process;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc;
-use std::sync::{Mutex, OnceLock};
+use std::sync::{self, Arc, Mutex, MutexGuard, OnceLock, TryLockError};
+use std::task;
 use std::thread;
+use std::time;
+
+#[cfg(feature = "async")]
+use futures_channel::oneshot;
+#[cfg(feature = "async")]
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::pin;
 
 use super::font;
+use super::local;
+use super::tcl;
 use super::toplevel;
 use super::widget;
 
 /// Reports an error in interacting with the Tk program.
 #[derive(Debug)]
-pub struct TkError {
-    #[allow(dead_code)]
-    message: String,
-}
-
-static TRACE_WISH: OnceLock<bool> = OnceLock::new();
-fn tracing() -> bool {
-    *TRACE_WISH.get().unwrap_or(&false)
-}
-
-static mut WISH: OnceLock<process::Child> = OnceLock::new();
-static mut OUTPUT: OnceLock<process::ChildStdout> = OnceLock::new();
-static mut SENDER: OnceLock<mpsc::Sender<String>> = OnceLock::new();
-
-// Kills the wish process - should be called to exit
-pub(super) fn kill_wish() {
-    unsafe {
-        WISH.get_mut()
-            .unwrap()
-            .kill()
-            .expect("Wish was unexpectedly already finished");
-    }
+pub enum TkError {
+    /// The wish program could not be found.
+    WishNotFound { program: String },
+    /// The wish program was found, but could not be started.
+    Spawn { program: String, source: io::Error },
+    /// A wish program has already been started.
+    AlreadyStarted,
+    /// The connection to wish has closed, e.g. because wish has died.
+    Disconnected,
+    /// A Tcl command failed: holds the Tcl error message and the
+    /// contents of `errorInfo`, tracing where the error occurred.
+    Tcl { message: String, error_info: String },
+    /// A response from wish could not be understood.
+    Parse { expected: String, response: String },
+    /// The exit status of the wish process could not be obtained.
+    Wait { source: io::Error },
+    /// The mainloop was started from within a callback, while events are
+    /// already being handled on this thread.
+    LoopRunning,
+    /// An event pattern could not be parsed, so was not sent to wish.
+    InvalidPattern { pattern: String },
 }
 
-/// Sends a message (tcl command) to wish.
-///
-/// Use with caution: the message must be valid tcl.
-///
-pub fn tell_wish(msg: &str) {
-    if tracing() {
-        println!("wish: {}", msg);
-    }
-    unsafe {
-        SENDER.get_mut().unwrap().send(String::from(msg)).unwrap();
-        SENDER.get_mut().unwrap().send(String::from("\n")).unwrap();
+impl fmt::Display for TkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TkError::WishNotFound { program } => {
+                write!(f, "could not find the {} program", program)
+            }
+            TkError::Spawn { program, source } => {
+                write!(f, "failed to start {} process: {}", program, source)
+            }
+            TkError::AlreadyStarted => write!(f, "wish has already been started"),
+            TkError::Disconnected => write!(f, "the connection to wish has closed"),
+            TkError::Tcl { message, .. } => write!(f, "Tcl error: {}", message),
+            TkError::Parse { expected, response } => {
+                write!(f, "expected {} from wish, got {:?}", expected, response)
+            }
+            TkError::Wait { source } => {
+                write!(f, "failed to wait for wish process: {}", source)
+            }
+            TkError::LoopRunning => write!(f, "the mainloop is already running"),
+            TkError::InvalidPattern { pattern } => {
+                write!(f, "invalid event pattern {:?}", pattern)
+            }
+        }
     }
 }
 
-/// Sends a message (tcl command) to wish and expects a result.
-/// Returns a result as a string
-///
-/// Use with caution: the message must be valid tcl.
-///
-pub fn ask_wish(msg: &str) -> String {
-    tell_wish(msg);
-
-    unsafe {
-        let mut input = [32; 10000]; // TODO - long inputs can get split?
-        if OUTPUT.get_mut().unwrap().read(&mut input).is_ok() {
-            if let Ok(input) = String::from_utf8(input.to_vec()) {
-                if tracing() {
-                    println!("---: {:?}", input.trim());
-                }
-                return input.trim().to_string();
-            }
+impl error::Error for TkError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            TkError::Spawn { source, .. } | TkError::Wait { source } => Some(source),
+            _ => None,
         }
     }
-
-    panic!("Eval-wish failed to get a result");
 }
 
-// -- Counter for making new ids
-
-fn next_static_id() -> &'static Mutex<i64> {
-    static NEXT_ID: OnceLock<Mutex<i64>> = OnceLock::new();
-
-    NEXT_ID.get_or_init(|| Mutex::new(0))
-}
+// -- Sessions
 
-/// Returns a new id string which can be used to name a new
-/// widget instance. The new id will be in reference to the
-/// parent, as is usual in Tk.
+/// A connection to a running wish process, owning the process and the
+/// channels used to talk to it.
 ///
-/// This is only for use when writing an extension library.
+/// The functions in this crate, such as [tell_wish] or the "make_WIDGET"
+/// constructors, act on the _current_ session: this is the session entered
+/// on the current thread, see [enter](TkSession::enter), or otherwise the
+/// default session created by [start_wish]. Callbacks are run with their
+/// own session entered.
 ///
-pub fn next_wid(parent: &str) -> String {
-    let mut nid = next_static_id().lock().unwrap();
-    *nid += 1;
-    if parent == "." {
-        format!(".r{}", nid)
-    } else {
-        format!("{}.r{}", parent, nid)
-    }
+/// Each session runs a separate wish process, so its windows are isolated
+/// from those of other sessions, and a new session can be started if an
+/// earlier one has ended or died.
+#[derive(Clone)]
+pub struct TkSession {
+    inner: Arc<Session>,
 }
 
-/// Returns a new variable name.
-///
-/// This is only for use when writing an extension library.
-///
-pub fn next_var() -> String {
-    let mut nid = next_static_id().lock().unwrap();
-    *nid += 1;
-    format!("::var{}", nid)
+// The transport used to communicate with wish, and the queue of events
+// read by the mainloop: the transport passes events onto the queue through
+// an EventSink, and the queue also holds tasks posted from other threads
+// through a UiHandle.
+struct Session {
+    trace: bool,
+    transport: Box<dyn Transport>,
+    events: Mutex<mpsc::Receiver<LoopMessage>>,
+    sink: EventSink,
+    loop_thread: Mutex<Option<thread::ThreadId>>,
+    exit_request: Mutex<Option<ExitReason>>,
+    shutdown_hooks: Mutex<Vec<ShutdownHook>>,
+    commands: Callbacks<()>,
+    toggles: Callbacks<bool>,
+    bindings: Callbacks<widget::TkEvent>,
+    filters: Callbacks<FilterEvent>,
+    values: Callbacks<f64>,
+    fonts: Callbacks<font::TkFont>,
+    // The children of the root created in this session: each widget of
+    // the session is one of them or within one.
+    windows: Mutex<HashSet<String>>,
 }
 
-pub(super) fn current_id() -> i64 {
-    let nid = next_static_id().lock().unwrap();
-    *nid
+// Marks the current thread as the one reading a session's events, until
+// dropped: callbacks on this thread then cannot wait for events.
+struct LoopThread<'a> {
+    session: &'a Session,
 }
 
-// -- Store for callback functions, such as on button clicks
+impl<'a> LoopThread<'a> {
+    fn enter(session: &'a Session) -> LoopThread<'a> {
+        *session.loop_thread.lock().unwrap() = Some(thread::current().id());
+        LoopThread { session }
+    }
+}
 
-type Callback0 = Box<(dyn Fn() + Send + 'static)>;
-pub(super) fn mk_callback0<F>(f: F) -> Callback0
-where
-    F: Fn() + Send + 'static,
-{
-    Box::new(f)
+impl Drop for LoopThread<'_> {
+    fn drop(&mut self) {
+        *self.session.loop_thread.lock().unwrap() = None;
+    }
 }
 
-fn static_callbacks0() -> &'static Mutex<HashMap<String, Callback0>> {
-    static CALLBACKS0: OnceLock<Mutex<HashMap<String, Callback0>>> = OnceLock::new();
+type ShutdownHook = Box<dyn FnOnce() + Send + 'static>;
+type Task = Box<dyn FnOnce() + Send + 'static>;
 
-    CALLBACKS0.get_or_init(|| Mutex::new(HashMap::new()))
+// An item on the queue read by the mainloop: an event from wish, a task
+// posted from another thread, or notice that wish has closed its output.
+enum LoopMessage {
+    Event(String),
+    Task(Task),
+    Closed,
 }
 
-pub(super) fn add_callback0(wid: &str, callback: Callback0) {
-    static_callbacks0()
-        .lock()
-        .unwrap()
-        .insert(String::from(wid), callback);
+impl fmt::Debug for LoopMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoopMessage::Event(message) => f.debug_tuple("Event").field(message).finish(),
+            LoopMessage::Task(_) => f.write_str("Task"),
+            LoopMessage::Closed => f.write_str("Closed"),
+        }
+    }
 }
 
-fn get_callback0(wid: &str) -> Option<Callback0> {
-    if let Some((_, command)) = static_callbacks0().lock().unwrap().remove_entry(wid) {
-        Some(command)
-    } else {
-        None
+impl fmt::Debug for TkSession {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TkSession")
+            .field("transport", &self.inner.transport)
+            .finish_non_exhaustive()
     }
 }
 
-fn eval_callback0(wid: &str) {
-    if let Some(command) = get_callback0(wid) {
-        command();
-        if !wid.contains("after") && // after commands apply once only
-            !static_callbacks0().lock().unwrap().contains_key(wid)
-        // do not overwrite if a replacement command added
-        {
-            add_callback0(wid, command);
-        }
-    } // TODO - error?
+impl PartialEq for TkSession {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
 }
 
-type Callback1Bool = Box<(dyn Fn(bool) + Send + 'static)>;
-pub(super) fn mk_callback1_bool<F>(f: F) -> Callback1Bool
-where
-    F: Fn(bool) + Send + 'static,
-{
-    Box::new(f)
+/// The connection through which a session sends Tcl commands to wish,
+/// and receives their results and events.
+///
+/// Sessions started with [TkSession::start_with] use a wish process: other
+/// transports can be used with [TkSession::with_transport], e.g. a
+/// [MockWish](crate::mock::MockWish) to test code without running wish.
+pub trait Transport: fmt::Debug + Send + Sync + 'static {
+    /// Called once, when a session is created with this transport, giving
+    /// the sink to pass events to.
+    fn attach(&self, events: EventSink);
+
+    /// Sends a command to be evaluated, without waiting for it.
+    fn tell(&self, msg: &str);
+
+    /// Sends a command to be evaluated, passing its result to `reply` once
+    /// known. Returns false, without calling `reply`, if wish has finished.
+    /// If wish finishes before replying, `reply` is dropped without being
+    /// called.
+    fn ask(&self, msg: &str, reply: Reply) -> bool;
+
+    /// Checks if commands can still be sent.
+    fn is_connected(&self) -> bool;
+
+    /// Ends wish immediately.
+    fn end(&self);
+
+    /// Waits for wish to finish, returning its exit status.
+    fn wait(&self) -> Result<process::ExitStatus, TkError>;
+
+    /// Returns the exit status of wish, if it has finished.
+    fn exit_status(&self) -> Option<process::ExitStatus>;
 }
 
-fn static_callbacks1bool() -> &'static Mutex<HashMap<String, Callback1Bool>> {
-    static CALLBACKS1BOOL: OnceLock<Mutex<HashMap<String, Callback1Bool>>> = OnceLock::new();
+/// Receives the result of a command sent by [Transport::ask].
+pub type Reply = Box<dyn FnOnce(Result<String, TkError>) + Send + 'static>;
 
-    CALLBACKS1BOOL.get_or_init(|| Mutex::new(HashMap::new()))
+/// Passes events from a [Transport] to its session's mainloop.
+#[derive(Clone, Debug)]
+pub struct EventSink {
+    sender: mpsc::Sender<LoopMessage>,
+    waker: Arc<Mutex<Option<task::Waker>>>,
 }
 
-pub(super) fn add_callback1_bool(wid: &str, callback: Callback1Bool) {
-    static_callbacks1bool()
-        .lock()
-        .unwrap()
-        .insert(String::from(wid), callback);
-}
+impl EventSink {
+    /// Queues an event message, as sent by `afrish::event` in Tcl. Returns
+    /// false if the session no longer exists.
+    pub fn event(&self, message: &str) -> bool {
+        self.send(LoopMessage::Event(String::from(message)))
+    }
 
-fn get_callback1_bool(wid: &str) -> Option<Callback1Bool> {
-    if let Some((_, command)) = static_callbacks1bool().lock().unwrap().remove_entry(wid) {
-        Some(command)
-    } else {
-        None
+    /// Tells the mainloop that wish has finished, so no more events can
+    /// arrive.
+    pub fn close(&self) {
+        self.send(LoopMessage::Closed);
     }
-}
 
-fn eval_callback1_bool(wid: &str, value: bool) {
-    if let Some(command) = get_callback1_bool(wid) {
-        command(value);
-        if !static_callbacks1bool().lock().unwrap().contains_key(wid) {
-            add_callback1_bool(wid, command);
+    // Queues the message, and wakes any task polling for it, as when
+    // polling a TkEventStream.
+    fn send(&self, message: LoopMessage) -> bool {
+        let sent = self.sender.send(message).is_ok();
+        if let Some(waker) = self.waker.lock().unwrap().as_ref() {
+            waker.wake_by_ref();
         }
-    } // TODO - error?
+        sent
+    }
 }
 
-type Callback1Event = Box<(dyn Fn(widget::TkEvent) + Send + 'static)>;
-pub(super) fn mk_callback1_event<F>(f: F) -> Callback1Event
-where
-    F: Fn(widget::TkEvent) + Send + 'static,
-{
-    Box::new(f)
+/// Restores the previously current session when dropped: returned by
+/// [enter](TkSession::enter).
+#[derive(Debug)]
+pub struct TkSessionGuard {
+    previous: Option<TkSession>,
 }
 
-// for bound events, key is widgetid/all + pattern, as multiple events can be
-// bound to same entity
-fn static_callbacks1event() -> &'static Mutex<HashMap<String, Callback1Event>> {
-    static CALLBACKS1EVENT: OnceLock<Mutex<HashMap<String, Callback1Event>>> = OnceLock::new();
+impl Drop for TkSessionGuard {
+    fn drop(&mut self) {
+        CURRENT_SESSION.with(|current| *current.borrow_mut() = self.previous.take());
+    }
+}
 
-    CALLBACKS1EVENT.get_or_init(|| Mutex::new(HashMap::new()))
+thread_local! {
+    static CURRENT_SESSION: RefCell<Option<TkSession>> = const { RefCell::new(None) };
 }
 
-pub(super) fn add_callback1_event(wid: &str, callback: Callback1Event) {
-    static_callbacks1event()
-        .lock()
-        .unwrap()
-        .insert(String::from(wid), callback);
+static DEFAULT_SESSION: Mutex<Option<TkSession>> = Mutex::new(None);
+
+// Returns the current session: panics if no wish process has been started.
+fn session() -> TkSession {
+    TkSession::current().expect("Wish has not been started")
 }
 
-fn get_callback1_event(wid: &str) -> Option<Callback1Event> {
-    if let Some((_, command)) = static_callbacks1event().lock().unwrap().remove_entry(wid) {
-        Some(command)
-    } else {
-        None
+impl TkSession {
+    /// Starts a new session with the given wish/tclkit program.
+    pub fn start_with(wish: &str) -> Result<TkSession, TkError> {
+        start_session(wish, false)
+    }
+
+    /// Starts a new session with the given wish/tclkit program with
+    /// debugging output enabled (wish interactions are reported to stdout).
+    pub fn trace_with(wish: &str) -> Result<TkSession, TkError> {
+        start_session(wish, true)
+    }
+
+    /// Creates a session communicating through the given transport, e.g.
+    /// a [MockWish](crate::mock::MockWish) for testing.
+    pub fn with_transport(transport: impl Transport) -> TkSession {
+        TkSession::new(Box::new(transport), false)
     }
-}
 
-fn eval_callback1_event(wid: &str, value: widget::TkEvent) {
-    if let Some(command) = get_callback1_event(wid) {
-        command(value);
-        if !static_callbacks1event().lock().unwrap().contains_key(wid) {
-            add_callback1_event(wid, command);
+    fn new(transport: Box<dyn Transport>, trace: bool) -> TkSession {
+        let (sender, receiver) = mpsc::channel();
+        let sink = EventSink {
+            sender,
+            waker: Arc::new(Mutex::new(None)),
+        };
+        transport.attach(sink.clone());
+
+        TkSession {
+            inner: Arc::new(Session {
+                trace,
+                transport,
+                events: Mutex::new(receiver),
+                sink,
+                loop_thread: Mutex::new(None),
+                exit_request: Mutex::new(None),
+                shutdown_hooks: Mutex::new(vec![]),
+                commands: Callbacks::new(),
+                toggles: Callbacks::new(),
+                bindings: Callbacks::new(),
+                filters: Callbacks::new(),
+                values: Callbacks::new(),
+                fonts: Callbacks::new(),
+                windows: Mutex::new(HashSet::new()),
+            }),
         }
-    } // TODO - error?
-}
+    }
 
-type Callback1Float = Box<(dyn Fn(f64) + Send + 'static)>;
-pub(super) fn mk_callback1_float<F>(f: F) -> Callback1Float
-where
-    F: Fn(f64) + Send + 'static,
-{
-    Box::new(f)
-}
+    /// Returns the session entered on this thread, or else the default
+    /// session, if one has been started.
+    pub fn current() -> Option<TkSession> {
+        CURRENT_SESSION
+            .with(|current| current.borrow().clone())
+            .or_else(|| DEFAULT_SESSION.lock().unwrap().clone())
+    }
 
-fn static_callbacks1float() -> &'static Mutex<HashMap<String, Callback1Float>> {
-    static CALLBACKS1FLOAT: OnceLock<Mutex<HashMap<String, Callback1Float>>> = OnceLock::new();
+    /// Makes this the current session on this thread, until the returned
+    /// guard is dropped. Widgets created in the meantime belong to this
+    /// session.
+    pub fn enter(&self) -> TkSessionGuard {
+        let previous = CURRENT_SESSION.with(|current| current.replace(Some(self.clone())));
+        TkSessionGuard { previous }
+    }
 
-    CALLBACKS1FLOAT.get_or_init(|| Mutex::new(HashMap::new()))
-}
+    /// Returns the top-level window of this session.
+    ///
+    /// As for all widgets, its methods act on the current session: enter
+    /// this session while using it.
+    pub fn root(&self) -> toplevel::TkTopLevel {
+        toplevel::TkTopLevel {
+            id: String::from("."),
+        }
+    }
 
-pub(super) fn add_callback1_float(wid: &str, callback: Callback1Float) {
-    static_callbacks1float()
-        .lock()
-        .unwrap()
-        .insert(String::from(wid), callback);
-}
+    /// Checks if the connection to wish is still open.
+    pub fn is_connected(&self) -> bool {
+        self.inner.transport.is_connected()
+    }
 
-fn get_callback1_float(wid: &str) -> Option<Callback1Float> {
-    if let Some((_, command)) = static_callbacks1float().lock().unwrap().remove_entry(wid) {
-        Some(command)
-    } else {
-        None
+    /// Sends a message (tcl command) to this session's wish: see [tell_wish].
+    pub fn tell_wish(&self, msg: &str) {
+        self.inner.check_widgets(msg);
+        if self.inner.trace {
+            println!("wish: {}", msg);
+        }
+        self.inner.transport.tell(msg);
+    }
+
+    /// Sends a message (tcl command) to this session's wish, and waits
+    /// for it to be evaluated: see [try_tell_wish].
+    pub fn try_tell_wish(&self, msg: &str) -> Result<(), TkError> {
+        self.try_ask_wish(msg).map(|_| ())
+    }
+
+    /// Sends a message (tcl command) to this session's wish and expects
+    /// a result: see [ask_wish].
+    pub fn ask_wish(&self, msg: &str) -> String {
+        self.try_ask_wish(msg).unwrap_or_default()
     }
-}
 
-fn eval_callback1_float(wid: &str, value: f64) {
-    if let Some(command) = get_callback1_float(wid) {
-        command(value);
-        if !static_callbacks1float().lock().unwrap().contains_key(wid) {
-            add_callback1_float(wid, command);
+    /// Sends a message (tcl command) to this session's wish and expects
+    /// a result: see [try_ask_wish].
+    pub fn try_ask_wish(&self, msg: &str) -> Result<String, TkError> {
+        match self.eval_wish(msg) {
+            Some(result) => result,
+            None => Err(TkError::Disconnected),
         }
-    } // TODO - error?
-}
+    }
 
-type Callback1Font = Box<(dyn Fn(font::TkFont) + Send + 'static)>;
-pub(super) fn mk_callback1_font<F>(f: F) -> Callback1Font
-where
-    F: Fn(font::TkFont) + Send + 'static,
-{
-    Box::new(f)
-}
+    // Evaluates the message within a catch, returning None if wish
+    // does not reply.
+    fn eval_wish(&self, msg: &str) -> Option<Result<String, TkError>> {
+        let (sender, receiver) = mpsc::channel();
+        let sent = self.send_query(
+            msg,
+            Box::new(move |result| {
+                let _ = sender.send(result);
+            }),
+        );
+        if !sent {
+            return None;
+        }
 
-fn static_callbacks1font() -> &'static Mutex<HashMap<String, Callback1Font>> {
-    static CALLBACKS1FONT: OnceLock<Mutex<HashMap<String, Callback1Font>>> = OnceLock::new();
+        receiver.recv().ok()
+    }
 
-    CALLBACKS1FONT.get_or_init(|| Mutex::new(HashMap::new()))
-}
+    // Sends the message to be evaluated within a catch, passing the result
+    // to given reply once it arrives: see Transport::ask.
+    fn send_query(&self, msg: &str, reply: Reply) -> bool {
+        self.inner.check_widgets(msg);
+        if self.inner.trace {
+            println!("wish: {}", msg);
+        }
+        let reply: Reply = if self.inner.trace {
+            Box::new(move |result| {
+                match &result {
+                    Ok(value) => println!("---: {:?}", value),
+                    Err(error) => println!("---: {}", error),
+                }
+                reply(result);
+            })
+        } else {
+            reply
+        };
 
-pub(super) fn add_callback1_font(wid: &str, callback: Callback1Font) {
-    static_callbacks1font()
-        .lock()
-        .unwrap()
-        .insert(String::from(wid), callback);
-}
+        self.inner.transport.ask(msg, reply)
+    }
 
-fn get_callback1_font(wid: &str) -> Option<Callback1Font> {
-    if let Some((_, command)) = static_callbacks1font().lock().unwrap().remove_entry(wid) {
-        Some(command)
-    } else {
-        None
+    /// Sends a message (tcl command) to this session's wish and returns a
+    /// future for the result: see [try_ask_wish_async].
+    #[cfg(feature = "async")]
+    pub fn try_ask_wish_async(
+        &self,
+        msg: &str,
+    ) -> impl Future<Output = Result<String, TkError>> + Send + 'static {
+        let (sender, receiver) = oneshot::channel();
+        let sent = self.send_query(
+            msg,
+            Box::new(move |result| {
+                let _ = sender.send(result);
+            }),
+        );
+
+        async move {
+            if !sent {
+                return Err(TkError::Disconnected);
+            }
+            receiver.await.unwrap_or(Err(TkError::Disconnected))
+        }
+    }
+
+    /// Sends a message (tcl command) to this session's wish and returns a
+    /// future for the result: see [ask_wish_async].
+    #[cfg(feature = "async")]
+    pub fn ask_wish_async(&self, msg: &str) -> impl Future<Output = String> + Send + 'static {
+        let result = self.try_ask_wish_async(msg);
+        async move { result.await.unwrap_or_default() }
     }
-}
 
-fn eval_callback1_font(wid: &str, value: font::TkFont) {
-    if let Some(command) = get_callback1_font(wid) {
-        command(value);
-        if !static_callbacks1font().lock().unwrap().contains_key(wid) {
-            add_callback1_font(wid, command);
+    /// Returns a stream of this session's events: see [TkEventStream].
+    #[cfg(feature = "async")]
+    pub fn event_stream(&self) -> TkEventStream {
+        TkEventStream {
+            session: self.clone(),
+            finished: false,
         }
-    } // TODO - error?
-}
+    }
 
-/// Loops while GUI events occur
-pub fn mainloop() {
-    unsafe {
+    /// Loops while GUI events occur in this session, with the session
+    /// entered so callbacks act on it.
+    ///
+    /// Returns once wish has finished, reporting why, or an error if the
+    /// wish process could not be waited on. Returns
+    /// [TkError::LoopRunning] if called from within a callback, as events
+    /// are already being handled.
+    pub fn mainloop(&self) -> Result<ExitReason, TkError> {
+        let _guard = self.enter();
+        let Some(events) = self.lock_events() else {
+            // -- called from within a callback, e.g. as a nested loop
+            return Err(TkError::LoopRunning);
+        };
+        let _loop = LoopThread::enter(&self.inner);
         loop {
-            let mut input = [32; 10000];
-            if OUTPUT.get_mut().unwrap().read(&mut input).is_ok() {
-                if let Ok(input) = String::from_utf8(input.to_vec()) {
-                    if tracing() {
-                        println!("Callback: {:?}", &input.trim());
-                    }
+            let message = events.recv().unwrap_or(LoopMessage::Closed);
+            match self.handle_message(message) {
+                Ok(Some(WishEvent::Exit(reason))) => return Ok(reason),
+                Ok(_) => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
 
-                    // here - do a match or similar on what was read from wish
-                    if input.starts_with("clicked") {
-                        // -- callbacks
-                        if let Some(n) = input.find(['\n', '\r']) {
-                            let widget = &input[8..n];
-                            eval_callback0(widget);
-                        }
-                    } else if input.starts_with("cb1b") {
-                        // -- callback 1 with bool
-                        let parts: Vec<&str> = input.split('-').collect();
-                        let widget = parts[1].trim();
-                        let value = parts[2].trim();
-                        eval_callback1_bool(widget, value == "1");
-                    } else if input.starts_with("cb1e") {
-                        // -- callback 1 with event
-                        let parts: Vec<&str> = input.split(':').collect();
-                        let widget_pattern = parts[1].trim();
-                        let x = parts[2].parse::<i64>().unwrap_or(0);
-                        let y = parts[3].parse::<i64>().unwrap_or(0);
-                        let root_x = parts[4].parse::<i64>().unwrap_or(0);
-                        let root_y = parts[5].parse::<i64>().unwrap_or(0);
-                        let height = parts[6].parse::<i64>().unwrap_or(0);
-                        let width = parts[7].parse::<i64>().unwrap_or(0);
-                        let key_code = parts[8].parse::<u64>().unwrap_or(0);
-                        let key_symbol = parts[9].parse::<String>().unwrap_or_default();
-                        let mouse_button = parts[10].parse::<u64>().unwrap_or(0);
-                        let event = widget::TkEvent {
-                            x,
-                            y,
-                            root_x,
-                            root_y,
-                            height,
-                            width,
-                            key_code,
-                            key_symbol,
-                            mouse_button,
-                        };
-                        eval_callback1_event(widget_pattern, event);
-                    } else if input.starts_with("cb1f") {
-                        // -- callback 1 with float
-                        let parts: Vec<&str> = input.split('-').collect();
-                        let widget = parts[1].trim();
-                        let value = parts[2].trim().parse::<f64>().unwrap_or(0.0);
-                        eval_callback1_float(widget, value);
-                    } else if let Some(font) = input.strip_prefix("font") {
-                        // -- callback 1 with font
-                        let font = font.trim();
-                        if let Ok(font) = font.parse::<font::TkFont>() {
-                            eval_callback1_font("font", font);
-                        }
-                    } else if input.starts_with("exit") {
-                        // -- wish has exited
-                        kill_wish();
-                        return; // exit loop and program
-                    }
-                }
+    /// Runs the [mainloop](TkSession::mainloop) with given application
+    /// state, which callbacks made with [app_command](local::app_command)
+    /// or [app_handler](local::app_handler) can change.
+    ///
+    /// Returns the state once wish has finished, with the reason.
+    pub fn mainloop_with<S: 'static>(&self, state: S) -> Result<(ExitReason, S), TkError> {
+        let previous = local::set_app_state(Some(Box::new(state)));
+        let result = self.mainloop();
+        let state = local::set_app_state(previous)
+            .and_then(|state| state.downcast::<S>().ok())
+            .expect("application state is restored after the mainloop");
+
+        result.map(|reason| (reason, *state))
+    }
+
+    /// Waits up to the given time for the next event from wish, calling
+    /// any callback registered for it, and returns the decoded event.
+    ///
+    /// Closures posted through a [UiHandle] are called while waiting.
+    /// Returns `None` if no event arrived in time, or if called from
+    /// within a callback, and
+    /// [WishEvent::Exit] once the session has finished.
+    pub fn poll_event(&self, timeout: time::Duration) -> Result<Option<WishEvent>, TkError> {
+        let _guard = self.enter();
+        let Some(events) = self.lock_events() else {
+            // -- called from within a callback, so cannot wait for events
+            return Ok(None);
+        };
+        let _loop = LoopThread::enter(&self.inner);
+        let deadline = time::Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(time::Instant::now());
+            let message = match events.recv_timeout(remaining) {
+                Ok(message) => message,
+                Err(mpsc::RecvTimeoutError::Timeout) => return Ok(None),
+                Err(mpsc::RecvTimeoutError::Disconnected) => LoopMessage::Closed,
+            };
+            if let Some(event) = self.handle_message(message)? {
+                return Ok(Some(event));
             }
         }
     }
-}
 
-/// Creates a connection with the "wish" program.
-pub fn start_wish() -> Result<toplevel::TkTopLevel, TkError> {
-    start_with("wish")
-}
+    /// Handles all events already received from wish, calling their
+    /// callbacks, and returns without waiting.
+    ///
+    /// Returns the reason the session finished, if it has.
+    pub fn process_pending(&self) -> Result<Option<ExitReason>, TkError> {
+        let _guard = self.enter();
+        let Some(events) = self.lock_events() else {
+            // -- called from within a callback, so leave events to the loop
+            return Ok(None);
+        };
+        let _loop = LoopThread::enter(&self.inner);
+        while let Ok(message) = events.try_recv() {
+            if let Some(WishEvent::Exit(reason)) = self.handle_message(message)? {
+                return Ok(Some(reason));
+            }
+        }
 
-/// Creates a connection with the given wish/tclkit program.
-pub fn start_with(wish: &str) -> Result<toplevel::TkTopLevel, TkError> {
-    if TRACE_WISH.set(false).is_ok() {
-        start_tk_connection(wish)
-    } else {
-        Err(TkError {
-            message: String::from("Failed to set trace option"),
-        })
+        Ok(None)
     }
-}
 
-/// Creates a connection with the given wish/tclkit program with
-/// debugging output enabled (wish interactions are reported to stdout).
-pub fn trace_with(wish: &str) -> Result<toplevel::TkTopLevel, TkError> {
-    if TRACE_WISH.set(true).is_ok() {
-        start_tk_connection(wish)
-    } else {
-        Err(TkError {
-            message: String::from("Failed to set trace option"),
-        })
+    /// Has wish process all its pending events and redraw its windows,
+    /// and then handles the resulting events, as [process_pending](TkSession::process_pending).
+    pub fn update(&self) -> Result<Option<ExitReason>, TkError> {
+        // -- any error means wish has finished, as reported below
+        let _ = self.try_tell_wish("update");
+        self.process_pending()
     }
-}
 
-/// Creates a connection with the given wish/tclkit program.
-fn start_tk_connection(wish: &str) -> Result<toplevel::TkTopLevel, TkError> {
-    let err_msg = format!("Do not start {} twice", wish);
-
-    unsafe {
-        if let Ok(wish_process) = process::Command::new(wish)
-            .stdin(process::Stdio::piped())
-            .stdout(process::Stdio::piped())
-            .spawn()
-        {
-            if WISH.set(wish_process).is_err() {
-                return Err(TkError { message: err_msg });
+    /// Has wish redraw its windows and complete any pending geometry
+    /// changes, without processing other events, and then handles events
+    /// already received, as [process_pending](TkSession::process_pending).
+    pub fn update_idletasks(&self) -> Result<Option<ExitReason>, TkError> {
+        let _ = self.try_tell_wish("update idletasks");
+        self.process_pending()
+    }
+
+    // Locks the event queue, unless this thread is already reading it, as
+    // when called from within a callback.
+    fn lock_events(&self) -> Option<MutexGuard<'_, mpsc::Receiver<LoopMessage>>> {
+        match self.inner.events.try_lock() {
+            Ok(events) => Some(events),
+            Err(TryLockError::WouldBlock)
+                if *self.inner.loop_thread.lock().unwrap() == Some(thread::current().id()) =>
+            {
+                None
             }
-        } else {
-            return Err(TkError {
-                message: format!("Failed to start {} process", wish),
-            });
-        };
+            Err(_) => Some(self.inner.events.lock().unwrap()),
+        }
+    }
 
-        let mut input = WISH.get_mut().unwrap().stdin.take().unwrap();
-        if OUTPUT
-            .set(WISH.get_mut().unwrap().stdout.take().unwrap())
-            .is_err()
-        {
-            return Err(TkError { message: err_msg });
+    // Handles one item from the event queue, calling the callback or task,
+    // and returns the decoded event, if any.
+    fn handle_message(&self, message: LoopMessage) -> Result<Option<WishEvent>, TkError> {
+        let input = match message {
+            LoopMessage::Event(input) => input,
+            LoopMessage::Task(task) => {
+                if self.exit_requested().is_none() {
+                    task();
+                }
+                return Ok(None);
+            }
+            LoopMessage::Closed => {
+                return self.finish().map(|reason| Some(WishEvent::Exit(reason)))
+            }
+        };
+        if self.inner.trace {
+            println!("Callback: {:?}", &input);
         }
 
-        // -- initial setup of Tcl/Tk environment
+        if self.exit_requested().is_some() {
+            // -- waiting for wish to exit, so ignore remaining events
+            return Ok(None);
+        }
+        if input == "exit" {
+            // -- the top-level window has been closed
+            self.run_shutdown_hooks();
+            self.end();
+            return Ok(Some(WishEvent::Exit(ExitReason::WindowClosed)));
+        }
+        let event = decode_event(&input);
+        eval_event(&event);
 
-        // set close button to output 'exit' message, so rust can close connection
-        input
-            .write_all(b"wm protocol . WM_DELETE_WINDOW { puts stdout {exit} ; flush stdout } \n")
-            .unwrap();
-        // remove the 'tearoff' menu option
-        input.write_all(b"option add *tearOff 0\n").unwrap();
-        // tcl function to help working with font chooser
-        input
-            .write_all(
-                b"proc font_choice {w font args} {
-            set res {font }
-            append res [font actual $font]
-                puts $res
-                flush stdout
-        }\n",
-            )
-            .unwrap();
-        // tcl function to help working with scale widget
-        input
-            .write_all(
-                b"proc scale_value {w value args} {
-            puts cb1f-$w-$value
-                flush stdout
-        }\n",
-            )
-            .unwrap();
+        Ok(Some(event))
+    }
 
-        // configure the communication encoding
-        input
-            .write_all(b"chan configure stdin -encoding utf-8\n")
-            .unwrap();
+    // Called once wish has closed its output, so no more events can arrive:
+    // returns the reason the session finished.
+    fn finish(&self) -> Result<ExitReason, TkError> {
+        let requested = self.exit_requested();
+        if requested.is_none() {
+            self.run_shutdown_hooks();
+        }
+        let status = self.inner.transport.wait()?;
 
-        let (sender, receiver) = mpsc::channel();
-        SENDER.set(sender).expect(&err_msg);
-
-        // create thread to receive strings to send on to wish
-        thread::spawn(move || loop {
-            let msg: Result<String, mpsc::RecvError> = receiver.recv();
-            if let Ok(msg) = msg {
-                input.write_all(msg.as_bytes()).unwrap();
-                input.write_all(b"\n").unwrap();
-            }
-        });
+        Ok(requested.unwrap_or(ExitReason::WishDied(status)))
     }
 
-    Ok(toplevel::TkTopLevel {
-        id: String::from("."),
-    })
-}
+    /// Returns a handle for running code on this session's mainloop from
+    /// other threads.
+    pub fn ui_handle(&self) -> UiHandle {
+        UiHandle {
+            sink: self.inner.sink.clone(),
+            session: Arc::downgrade(&self.inner),
+        }
+    }
 
-/// Used to cleanly end the wish process and current rust program.
-pub fn end_wish() {
-    kill_wish();
-    process::exit(0);
-}
+    /// Asks wish to exit, after running any shutdown hooks: a running
+    /// mainloop will return [ExitReason::Quit].
+    pub fn quit(&self) {
+        if self.request_exit(ExitReason::Quit) {
+            self.run_shutdown_hooks();
+            self.tell_wish("exit");
+        }
+    }
 
-// Splits tcl string where items can be single words or grouped in {..}
-pub(super) fn split_items(text: &str) -> Vec<String> {
-    let mut result: Vec<String> = vec![];
+    /// Registers a command to call when the session shuts down: on
+    /// [quit](TkSession::quit), when the top-level window is closed, or
+    /// if wish dies. Hooks are not called by [end](TkSession::end).
+    pub fn on_shutdown(&self, command: impl FnOnce() + Send + 'static) {
+        self.inner
+            .shutdown_hooks
+            .lock()
+            .unwrap()
+            .push(Box::new(command));
+    }
 
-    let mut remaining = text.trim();
-    while !remaining.is_empty() {
-        if let Some(start) = remaining.find('{') {
-            // -- add any words before first {
-            for word in remaining[..start].split_whitespace() {
-                result.push(String::from(word));
-            }
+    /// Ends the wish process of this session immediately: a running
+    /// mainloop will return [ExitReason::Ended].
+    pub fn end(&self) {
+        self.request_exit(ExitReason::Ended);
+        self.inner.transport.end();
+    }
 
-            if let Some(end) = remaining.find('}') {
-                result.push(String::from(&remaining[start + 1..end]));
-                remaining = remaining[end + 1..].trim();
-            } else {
-                // TODO keep what we have
-                break; // panic!("Incorrectly formed font family string");
-            }
+    // Records why the session is finishing, unless already finishing:
+    // returns true if this is the first request.
+    fn request_exit(&self, reason: ExitReason) -> bool {
+        let mut request = self.inner.exit_request.lock().unwrap();
+        if request.is_none() {
+            *request = Some(reason);
+            true
         } else {
-            // no { }, so just split all the words and end
-            for word in remaining.split_whitespace() {
-                result.push(String::from(word));
-            }
-            break;
+            false
         }
     }
 
-    result
-}
+    fn exit_requested(&self) -> Option<ExitReason> {
+        *self.inner.exit_request.lock().unwrap()
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Calls each shutdown hook, once.
+    fn run_shutdown_hooks(&self) {
+        let hooks = std::mem::take(&mut *self.inner.shutdown_hooks.lock().unwrap());
+        for hook in hooks {
+            hook();
+        }
+    }
 
-    #[test]
-    fn split_items_1() {
-        let result = split_items("");
-        assert_eq!(0, result.len());
+    /// Returns the exit status of this session's wish process, if it
+    /// has finished.
+    pub fn exit_status(&self) -> Option<process::ExitStatus> {
+        self.inner.transport.exit_status()
     }
+}
 
-    #[test]
-    fn split_items_2() {
-        let result = split_items("abc");
-        assert_eq!(1, result.len());
-        assert_eq!("abc", result[0]);
+/// A handle for running closures on the mainloop from other threads, e.g.
+/// to update widgets with the results of work done in the background.
+///
+/// Posted closures are queued with GUI events, and are called in order
+/// between events and callbacks, with the session entered: they can use
+/// widgets, register callbacks and set timers with
+/// [after](crate::timer::after) as a callback would.
+///
+/// ```ignore
+/// let ui = afrish::ui_handle();
+/// let label = afrish::make_label(&root);
+/// std::thread::spawn(move || {
+///     let text = long_computation();
+///     ui.post(move || label.text(&text)).unwrap();
+/// });
+/// afrish::mainloop().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct UiHandle {
+    sink: EventSink,
+    session: sync::Weak<Session>,
+}
+
+impl fmt::Debug for UiHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UiHandle").finish_non_exhaustive()
     }
+}
 
-    #[test]
-    fn split_items_3() {
-        let result = split_items("  abc  def  ");
-        assert_eq!(2, result.len());
-        assert_eq!("abc", result[0]);
-        assert_eq!("def", result[1]);
+impl UiHandle {
+    /// Queues the closure to be called on the mainloop, waking the loop if
+    /// it is waiting for events.
+    ///
+    /// Returns an error if the session has finished. Closures posted before
+    /// the mainloop starts are called once it does.
+    pub fn post(&self, task: impl FnOnce() + Send + 'static) -> Result<(), TkError> {
+        match self.session.upgrade() {
+            Some(session) if session.transport.is_connected() => {
+                if self.sink.send(LoopMessage::Task(Box::new(task))) {
+                    Ok(())
+                } else {
+                    Err(TkError::Disconnected)
+                }
+            }
+            _ => Err(TkError::Disconnected),
+        }
     }
 
-    #[test]
-    fn split_items_4() {
-        let result = split_items("{abc def}");
-        assert_eq!(1, result.len());
-        assert_eq!("abc def", result[0]);
+    /// Calls the closure on the mainloop, and waits for its result.
+    ///
+    /// If called from the mainloop itself, e.g. within a callback, the
+    /// closure is called directly. Returns an error if the session finishes
+    /// before the closure is called.
+    pub fn post_and_wait<T>(&self, task: impl FnOnce() -> T + Send + 'static) -> Result<T, TkError>
+    where
+        T: Send + 'static,
+    {
+        if let Some(session) = self.session.upgrade() {
+            if *session.loop_thread.lock().unwrap() == Some(thread::current().id()) {
+                return Ok(task());
+            }
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.post(move || {
+            let _ = sender.send(task());
+        })?;
+        receiver.recv().map_err(|_| TkError::Disconnected)
     }
+}
 
-    #[test]
-    fn split_items_5() {
-        let result = split_items("{abc def} xy_z {another}");
-        assert_eq!(3, result.len());
-        assert_eq!("abc def", result[0]);
-        assert_eq!("xy_z", result[1]);
-        assert_eq!("another", result[2]);
+/// A stream of events from wish, for use in place of the mainloop by
+/// programs using async/await: available with the "async" feature.
+///
+/// As with the mainloop, polling the stream calls the callbacks registered
+/// for each event, e.g. by [command](crate::TkButton::command) or
+/// [bind](widget::TkWidget::bind), and closures posted through a
+/// [UiHandle], before the decoded event is returned. The stream ends after
+/// returning [WishEvent::Exit].
+///
+/// The stream should not be polled while a mainloop is running for the
+/// same session.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct TkEventStream {
+    session: TkSession,
+    finished: bool,
+}
+
+#[cfg(feature = "async")]
+impl futures_core::Stream for TkEventStream {
+    type Item = Result<WishEvent, TkError>;
+
+    fn poll_next(
+        mut self: pin::Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> task::Poll<Option<Self::Item>> {
+        if self.finished {
+            return task::Poll::Ready(None);
+        }
+
+        let session = self.session.clone();
+        *session.inner.sink.waker.lock().unwrap() = Some(cx.waker().clone());
+        let _guard = session.enter();
+        let Ok(events) = session.inner.events.try_lock() else {
+            // -- events are being read elsewhere
+            return task::Poll::Pending;
+        };
+        let _loop = LoopThread::enter(&session.inner);
+        while let Ok(message) = events.try_recv() {
+            match session.handle_message(message) {
+                Ok(Some(event)) => {
+                    self.finished = matches!(event, WishEvent::Exit(_));
+                    return task::Poll::Ready(Some(Ok(event)));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    self.finished = true;
+                    return task::Poll::Ready(Some(Err(error)));
+                }
+            }
+        }
+
+        task::Poll::Pending
+    }
+}
+
+/// Reports why the mainloop returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The user closed the top-level window.
+    WindowClosed,
+    /// The program called [quit].
+    Quit,
+    /// The session was ended, by [end_wish] or [TkSession::end].
+    Ended,
+    /// The wish process exited or closed its output unexpectedly, e.g.
+    /// because it crashed: holds its exit status.
+    WishDied(process::ExitStatus),
+}
+
+/// Sends a message (tcl command) to wish.
+///
+/// Use with caution: the message must be valid tcl.
+///
+pub fn tell_wish(msg: &str) {
+    session().tell_wish(msg);
+}
+
+/// Sends a message (tcl command) to wish, and waits for it to be evaluated.
+/// Returns an error if the command fails, holding the Tcl error message and
+/// `errorInfo`.
+///
+pub fn try_tell_wish(msg: &str) -> Result<(), TkError> {
+    session().try_tell_wish(msg)
+}
+
+/// Sends a message (tcl command) to wish and expects a result.
+/// Returns the result of evaluating the command as a string.
+///
+/// The reply is delivered separately to any events, so this can
+/// be safely called from within callbacks.
+///
+/// If the command fails, or wish has finished, an empty string is
+/// returned: use [try_ask_wish] to find out why.
+///
+/// Use with caution: the message must be valid tcl.
+///
+pub fn ask_wish(msg: &str) -> String {
+    session().ask_wish(msg)
+}
+
+/// Sends a message (tcl command) to wish and expects a result.
+/// Returns the result of evaluating the command as a string, or an error
+/// holding the Tcl error message and `errorInfo` if the command fails.
+///
+pub fn try_ask_wish(msg: &str) -> Result<String, TkError> {
+    session().try_ask_wish(msg)
+}
+
+fn next_reply_id() -> u64 {
+    static NEXT_REPLY: Mutex<u64> = Mutex::new(0);
+
+    let mut nid = NEXT_REPLY.lock().unwrap();
+    *nid += 1;
+    *nid
+}
+
+// -- Framed messages from wish
+
+// A message read from wish: the reply to a query with given id, the error
+// message and errorInfo of a failed query, or an event.
+#[derive(Debug, PartialEq)]
+enum Message {
+    Reply(u64, String),
+    Error(u64, String, String),
+    Event(String),
+}
+
+// Reads the next message written by the afrish procs. Each message is
+// a header line, "reply ID LENGTH", "error ID LENGTH LENGTH" or
+// "event LENGTH", followed by the given number of bytes of utf-8 text
+// and a newline. Any other output, e.g. from a script writing to stdout
+// directly, is skipped.
+// -- returns None once wish has closed its output
+fn read_message(reader: &mut impl BufRead, trace: bool) -> Option<Message> {
+    let mut line = vec![];
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => {}
+        }
+
+        let header = String::from_utf8_lossy(&line);
+        let parts: Vec<&str> = header.split_whitespace().collect();
+        let numbers: Vec<usize> = parts
+            .iter()
+            .skip(1)
+            .filter_map(|part| part.parse::<usize>().ok())
+            .collect();
+        if parts.is_empty() || numbers.len() != parts.len() - 1 {
+            if trace {
+                println!("Ignored: {:?}", header.trim());
+            }
+            continue;
+        }
+
+        let mut read_text = |length: usize| -> Option<String> {
+            let mut text = vec![0; length];
+            reader.read_exact(&mut text).ok()?;
+            Some(String::from_utf8_lossy(&text).into_owned())
+        };
+
+        let message = match (parts[0], &numbers[..]) {
+            ("reply", &[id, length]) => Message::Reply(id as u64, read_text(length)?),
+            ("error", &[id, message, info]) => {
+                Message::Error(id as u64, read_text(message)?, read_text(info)?)
+            }
+            ("event", &[length]) => Message::Event(read_text(length)?),
+            _ => continue,
+        };
+        // -- skip the newline terminating the payload
+        read_text(1)?;
+
+        return Some(message);
+    }
+}
+
+// -- Counter for making new ids
+
+fn next_static_id() -> &'static Mutex<i64> {
+    static NEXT_ID: OnceLock<Mutex<i64>> = OnceLock::new();
+
+    NEXT_ID.get_or_init(|| Mutex::new(0))
+}
+
+/// Returns a new id string which can be used to name a new
+/// widget instance. The new id will be in reference to the
+/// parent, as is usual in Tk.
+///
+/// This is only for use when writing an extension library.
+///
+pub fn next_wid(parent: &str) -> String {
+    let mut nid = next_static_id().lock().unwrap();
+    *nid += 1;
+    if parent == "." {
+        let id = format!(".r{}", nid);
+        if let Some(session) = TkSession::current() {
+            session.inner.windows.lock().unwrap().insert(id.clone());
+        }
+        id
+    } else {
+        format!("{}.r{}", parent, nid)
+    }
+}
+
+/// Returns a new variable name.
+///
+/// This is only for use when writing an extension library.
+///
+pub fn next_var() -> String {
+    let mut nid = next_static_id().lock().unwrap();
+    *nid += 1;
+    format!("::var{}", nid)
+}
+
+pub(super) fn current_id() -> i64 {
+    let nid = next_static_id().lock().unwrap();
+    *nid
+}
+
+// -- Store for callback functions, such as on button clicks
+
+/// Refers to a registered callback, such as the command of a button or a
+/// bound event: returned when the callback is registered.
+///
+/// Callbacks are also removed when their widget is destroyed.
+#[derive(Clone, Debug)]
+pub struct CallbackHandle {
+    session: sync::Weak<Session>,
+    key: String,
+    serial: u64,
+}
+
+impl CallbackHandle {
+    /// Removes the callback, so it is no longer called, and drops it.
+    pub fn unregister(&self) {
+        if let Some(session) = self.session.upgrade() {
+            session.remove_callback(&self.key, self.serial);
+        }
+    }
+
+    /// Returns true if the callback is still registered.
+    pub fn is_registered(&self) -> bool {
+        match self.session.upgrade() {
+            Some(session) => session.has_callback(&self.key, self.serial),
+            None => false,
+        }
+    }
+
+    // Returns the session the callback was registered in, if it exists.
+    pub(super) fn session(&self) -> Option<TkSession> {
+        self.session.upgrade().map(|inner| TkSession { inner })
+    }
+}
+
+type Function<A> = Arc<Mutex<Box<dyn Fn(A) + Send + 'static>>>;
+
+// A registered callback: the widget owning it, and whether it is called
+// once only, as for timers.
+struct Callback<A> {
+    serial: u64,
+    owner: String,
+    once: bool,
+    function: Function<A>,
+}
+
+// The callbacks taking one type of argument, keyed by the name used in
+// event messages from wish: a widget id, tag pattern or timer name.
+pub(super) struct Callbacks<A> {
+    entries: Mutex<HashMap<String, Callback<A>>>,
+}
+
+impl<A> Callbacks<A> {
+    fn new() -> Callbacks<A> {
+        Callbacks {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Returns the function for given key, removing it if called once only.
+    fn take(&self, key: &str) -> Option<Function<A>> {
+        let mut entries = self.entries.lock().unwrap();
+        let callback = entries.get(key)?;
+        if callback.once {
+            entries.remove(key).map(|callback| callback.function)
+        } else {
+            Some(callback.function.clone())
+        }
+    }
+
+    fn contains(&self, key: &str, serial: u64) -> bool {
+        matches!(self.entries.lock().unwrap().get(key), Some(callback) if callback.serial == serial)
+    }
+
+    fn remove(&self, key: &str, serial: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        if matches!(entries.get(key), Some(callback) if callback.serial == serial) {
+            entries.remove(key);
+        }
+    }
+
+    // Removes the callback with given key, if any.
+    fn remove_key(&self, key: &str) {
+        self.entries.lock().unwrap().remove(key);
+    }
+
+    // Removes callbacks owned by given widget or its children.
+    fn purge(&self, widget: &str) {
+        let children = format!("{}.", widget.trim_end_matches('.'));
+        self.entries.lock().unwrap().retain(|_, callback| {
+            callback.owner != widget && !callback.owner.starts_with(&children)
+        });
+    }
+}
+
+/// The argument types of callbacks: each type has its own store.
+pub(super) trait CallbackArg: Sized + 'static {
+    fn callbacks(session: &TkSession) -> &Callbacks<Self>;
+}
+
+impl CallbackArg for () {
+    fn callbacks(session: &TkSession) -> &Callbacks<()> {
+        &session.inner.commands
+    }
+}
+
+impl CallbackArg for bool {
+    fn callbacks(session: &TkSession) -> &Callbacks<bool> {
+        &session.inner.toggles
+    }
+}
+
+impl CallbackArg for widget::TkEvent {
+    fn callbacks(session: &TkSession) -> &Callbacks<widget::TkEvent> {
+        &session.inner.bindings
+    }
+}
+
+// A bound event which wish is waiting on, with the number of its request
+// for a decision, or 0 if wish is not waiting.
+pub(super) struct FilterEvent {
+    pub(super) event: widget::TkEvent,
+    pub(super) request: u64,
+}
+
+impl CallbackArg for FilterEvent {
+    fn callbacks(session: &TkSession) -> &Callbacks<FilterEvent> {
+        &session.inner.filters
+    }
+}
+
+impl CallbackArg for f64 {
+    fn callbacks(session: &TkSession) -> &Callbacks<f64> {
+        &session.inner.values
+    }
+}
+
+impl CallbackArg for font::TkFont {
+    fn callbacks(session: &TkSession) -> &Callbacks<font::TkFont> {
+        &session.inner.fonts
+    }
+}
+
+impl Session {
+    // Panics, in debug builds, if the command names a widget created in
+    // another session: widget ids are checked among the command's first
+    // words, where widget methods place them.
+    fn check_widgets(&self, msg: &str) {
+        if cfg!(debug_assertions) {
+            let windows = self.windows.lock().unwrap();
+            for word in msg.split_whitespace().take(3) {
+                let word = word.trim_matches(['{', '}']);
+                if let Some(window) = window_of(word) {
+                    assert!(
+                        windows.contains(window),
+                        "{:?} uses a widget from another session: enter its session first",
+                        msg
+                    );
+                }
+            }
+        }
+    }
+
+    fn remove_callback(&self, key: &str, serial: u64) {
+        self.commands.remove(key, serial);
+        self.toggles.remove(key, serial);
+        self.bindings.remove(key, serial);
+        self.filters.remove(key, serial);
+        self.values.remove(key, serial);
+        self.fonts.remove(key, serial);
+    }
+
+    fn has_callback(&self, key: &str, serial: u64) -> bool {
+        self.commands.contains(key, serial)
+            || self.toggles.contains(key, serial)
+            || self.bindings.contains(key, serial)
+            || self.filters.contains(key, serial)
+            || self.values.contains(key, serial)
+            || self.fonts.contains(key, serial)
+    }
+
+    fn purge_callbacks(&self, widget: &str) {
+        self.commands.purge(widget);
+        self.toggles.purge(widget);
+        self.bindings.purge(widget);
+        self.filters.purge(widget);
+        self.values.purge(widget);
+        self.fonts.purge(widget);
+    }
+}
+
+// Returns the child of the root containing the widget with given id, if
+// the id was made by next_wid.
+fn window_of(id: &str) -> Option<&str> {
+    let rest = id.strip_prefix(".r")?;
+    let length = rest.find('.').unwrap_or(rest.len());
+    if length == 0 || !rest[..length].bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(&id[..length + 2])
+}
+
+// Registers a callback under given key, replacing any callback already
+// registered with that key. The callback is removed when the owning
+// widget is destroyed: an empty owner is never destroyed.
+fn register<A: CallbackArg>(
+    owner: &str,
+    key: &str,
+    once: bool,
+    command: impl Fn(A) + Send + 'static,
+) -> CallbackHandle {
+    static NEXT_SERIAL: AtomicU64 = AtomicU64::new(1);
+
+    let session = session();
+    let serial = NEXT_SERIAL.fetch_add(1, Ordering::Relaxed);
+    A::callbacks(&session).entries.lock().unwrap().insert(
+        String::from(key),
+        Callback {
+            serial,
+            owner: String::from(owner),
+            once,
+            function: Arc::new(Mutex::new(Box::new(command))),
+        },
+    );
+
+    CallbackHandle {
+        session: Arc::downgrade(&session.inner),
+        key: String::from(key),
+        serial,
+    }
+}
+
+/// Registers a callback for events with given key, owned by given widget.
+pub(super) fn add_callback<A: CallbackArg>(
+    owner: &str,
+    key: &str,
+    command: impl Fn(A) + Send + 'static,
+) -> CallbackHandle {
+    register(owner, key, false, command)
+}
+
+/// Registers a callback called once only, for events with given key.
+pub(super) fn add_callback_once<A: CallbackArg>(
+    key: &str,
+    command: impl Fn(A) + Send + 'static,
+) -> CallbackHandle {
+    register("", key, true, command)
+}
+
+/// Removes the command registered for events with given key, if any.
+pub(super) fn remove_command(key: &str) {
+    session().inner.commands.remove_key(key);
+}
+
+/// Removes the callbacks owned by given widget or its children.
+pub(super) fn purge_callbacks(widget: &str) {
+    session().inner.purge_callbacks(widget);
+}
+
+/// Destroys given widget, and removes its callbacks and those of its
+/// children once any events already sent by wish have been handled.
+pub(super) fn destroy(widget: &str) {
+    let session = session();
+    // waiting for the reply means any <Destroy> events are queued first
+    let _ = session.try_ask_wish(&format!("destroy {}", widget));
+    session.inner.sink.event(&format!("destroyed-{}", widget));
+}
+
+// Calls the callback registered for given key, if any, returning true
+// if it was called. A callback that is already running, e.g. when events
+// are processed from within the callback, is not called again.
+fn eval_callback<A: CallbackArg>(key: &str, value: A) -> bool {
+    let function = A::callbacks(&session()).take(key);
+    if let Some(function) = function {
+        if let Ok(function) = function.try_lock() {
+            function(value);
+            return true;
+        }
+    }
+    false
+}
+
+/// Tells wish how to continue with a filtered event it is waiting on.
+pub(super) fn decide(request: u64, propagation: widget::Propagation) {
+    if request > 0 {
+        tell_wish(&format!("afrish::decide {} {}", request, propagation));
+    }
+}
+
+/// An event received from wish, as returned by [poll_event].
+#[derive(Clone, Debug, PartialEq)]
+pub enum WishEvent {
+    /// A command, e.g. of a button or timer, for widget with given id.
+    Command(String),
+    /// A command with a selected state, e.g. of a check button.
+    Toggled(String, bool),
+    /// A bound event, for the binding with given [key](widget::BindingHandle::key).
+    Bound(String, widget::TkEvent),
+    /// A bound event for a [filter](widget::TkWidget::bind_filter), which
+    /// wish may be waiting to continue: holds the number of its request.
+    Filtered(String, widget::TkEvent, u64),
+    /// A command with a numeric value, e.g. of a scale.
+    Value(String, f64),
+    /// A font chosen in the font chooser.
+    Font(font::TkFont),
+    /// A widget with given id has been destroyed.
+    Destroyed(String),
+    /// Any other message sent by wish.
+    Other(String),
+    /// The session has finished, for the given reason.
+    Exit(ExitReason),
+}
+
+// Decodes the given event message.
+fn decode_event(input: &str) -> WishEvent {
+    if let Some(widget) = input.strip_prefix("clicked-") {
+        // -- callbacks
+        WishEvent::Command(String::from(widget))
+    } else if let Some(widget) = input.strip_prefix("destroyed-") {
+        WishEvent::Destroyed(String::from(widget))
+    } else if input.starts_with("cb1b") {
+        // -- callback 1 with bool
+        let parts: Vec<&str> = input.split('-').collect();
+        let widget = parts[1].trim();
+        let value = parts[2].trim();
+        WishEvent::Toggled(String::from(widget), value == "1")
+    } else if input.starts_with("cb1e ") {
+        // -- callback 1 with event
+        let fields = tcl::list_items(input);
+        let (widget_pattern, event) = decode_bound(fields.get(1..).unwrap_or_default());
+        WishEvent::Bound(widget_pattern, event)
+    } else if input.starts_with("cb1p ") {
+        // -- callback 1 with event, waiting on its propagation
+        let fields = tcl::list_items(input);
+        let request = fields.get(1).and_then(|request| request.parse().ok());
+        let (widget_pattern, event) = decode_bound(fields.get(2..).unwrap_or_default());
+        WishEvent::Filtered(widget_pattern, event, request.unwrap_or(0))
+    } else if input.starts_with("cb1f") {
+        // -- callback 1 with float
+        let parts: Vec<&str> = input.split('-').collect();
+        let widget = parts[1].trim();
+        let value = parts[2].trim().parse::<f64>().unwrap_or(0.0);
+        WishEvent::Value(String::from(widget), value)
+    } else if let Some(Ok(font)) = input
+        .strip_prefix("font")
+        .map(|font| font.trim().parse::<font::TkFont>())
+    {
+        // -- callback 1 with font
+        WishEvent::Font(font)
+    } else {
+        WishEvent::Other(String::from(input))
+    }
+}
+
+// Decodes the key and fields of a bound event: Tk gives "??" for fields
+// not applying to the event.
+fn decode_bound(fields: &[String]) -> (String, widget::TkEvent) {
+    let field = |index: usize| match fields.get(index).map(String::as_str) {
+        Some("??") | None => "",
+        Some(field) => field,
+    };
+    let event = widget::TkEvent {
+        x: field(1).parse::<i64>().unwrap_or(0),
+        y: field(2).parse::<i64>().unwrap_or(0),
+        root_x: field(3).parse::<i64>().unwrap_or(0),
+        root_y: field(4).parse::<i64>().unwrap_or(0),
+        height: field(5).parse::<i64>().unwrap_or(0),
+        width: field(6).parse::<i64>().unwrap_or(0),
+        key_code: field(7).parse::<u64>().unwrap_or(0),
+        key_symbol: String::from(field(8)),
+        mouse_button: field(9).parse::<u64>().unwrap_or(0),
+        unicode: String::from(field(10)),
+        modifiers: widget::Modifiers::from_bits(field(11).parse::<u32>().unwrap_or(0)),
+        delta: field(12).parse::<i64>().unwrap_or(0),
+        widget: String::from(field(13)),
+        time: field(14).parse::<u64>().unwrap_or(0),
+        keysym_number: field(15).parse::<u64>().unwrap_or(0),
+        data: String::from(field(16)),
+        event_type: field(17).parse::<u64>().unwrap_or(0),
+    };
+
+    (String::from(field(0)), event)
+}
+
+// Calls the callback registered for the given event.
+fn eval_event(event: &WishEvent) {
+    let _ = match event {
+        WishEvent::Command(widget) => eval_callback(widget, ()),
+        WishEvent::Toggled(widget, value) => eval_callback(widget, *value),
+        WishEvent::Bound(widget_pattern, event) => eval_callback(widget_pattern, event.clone()),
+        WishEvent::Filtered(widget_pattern, event, request) => {
+            let filter = FilterEvent {
+                event: event.clone(),
+                request: *request,
+            };
+            if !eval_callback(widget_pattern, filter) {
+                // -- the filter has been removed, or is already running
+                decide(*request, widget::Propagation::Continue);
+            }
+            true
+        }
+        WishEvent::Value(widget, value) => eval_callback(widget, *value),
+        WishEvent::Font(font) => eval_callback("font", font.clone()),
+        WishEvent::Destroyed(widget) => {
+            purge_callbacks(widget);
+            true
+        }
+        WishEvent::Other(_) | WishEvent::Exit(_) => true,
+    };
+}
+
+/// Loops while GUI events occur.
+///
+/// Returns once wish has finished, reporting whether the window was closed,
+/// [end_wish] was called, or wish died: see [ExitReason].
+pub fn mainloop() -> Result<ExitReason, TkError> {
+    session().mainloop()
+}
+
+/// Loops while GUI events occur, giving callbacks access to the
+/// application state: see [TkSession::mainloop_with] and the [local]
+/// module.
+pub fn mainloop_with<S: 'static>(state: S) -> Result<(ExitReason, S), TkError> {
+    session().mainloop_with(state)
+}
+
+/// Creates a connection with the "wish" program.
+pub fn start_wish() -> Result<toplevel::TkTopLevel, TkError> {
+    start_with("wish")
+}
+
+/// Creates a connection with the given wish/tclkit program.
+pub fn start_with(wish: &str) -> Result<toplevel::TkTopLevel, TkError> {
+    start_default(wish, false)
+}
+
+/// Creates a connection with the given wish/tclkit program with
+/// debugging output enabled (wish interactions are reported to stdout).
+pub fn trace_with(wish: &str) -> Result<toplevel::TkTopLevel, TkError> {
+    start_default(wish, true)
+}
+
+// Starts the default session used by the free functions: a new default
+// session can only be started once the previous one has disconnected.
+fn start_default(wish: &str, trace: bool) -> Result<toplevel::TkTopLevel, TkError> {
+    let mut default = DEFAULT_SESSION.lock().unwrap();
+    if default
+        .as_ref()
+        .is_some_and(|session| session.is_connected())
+    {
+        return Err(TkError::AlreadyStarted);
+    }
+    let session = start_session(wish, trace)?;
+    let root = session.root();
+    *default = Some(session);
+
+    Ok(root)
+}
+
+// Tcl procs used to frame all output to rust, so that replies to queries
+// and event messages can be read back whole, whatever their contents.
+const FRAMING_PROCS: &str = r#"
+chan configure stdout -translation binary
+namespace eval afrish {
+    variable asking 0
+    variable requests 0
+    variable filter_timeout 500
+}
+proc afrish::send {header payload} {
+    set payload [encoding convertto utf-8 $payload]
+    puts -nonewline stdout "$header [string length $payload]\n$payload\n"
+    flush stdout
+}
+proc afrish::event {message} {
+    afrish::send event $message
+}
+proc afrish::bind {command pattern args} {
+    if {![llength $args]} {
+        return [{*}$command $pattern]
+    }
+    set script [lindex $args 0]
+    if {[string index $script 0] eq "+"} {
+        set script [string range $script 1 end]
+        set current [{*}$command $pattern]
+        if {$current ne ""} {
+            set script "$current\n$script"
+        }
+    }
+    {*}$command $pattern $script
+}
+proc afrish::unbind {command pattern script} {
+    catch {
+        set scripts [split [{*}$command $pattern] \n]
+        set scripts [lsearch -all -inline -exact -not $scripts $script]
+        {*}$command $pattern [join $scripts \n]
+    }
+}
+proc afrish::every {interval key w} {
+    # -- stop once the owner is destroyed
+    if {![winfo exists $w]} {
+        return
+    }
+    after $interval [list afrish::every $interval $key $w]
+    afrish::event clicked-$key
+}
+proc afrish::throttle {key interval} {
+    variable throttled
+    # -- within an interval, call once more at its end
+    if {[info exists throttled($key)]} {
+        set throttled($key) 1
+        return
+    }
+    set throttled($key) 0
+    after $interval [list afrish::throttled $key $interval]
+    afrish::event clicked-$key
+}
+proc afrish::throttled {key interval} {
+    variable throttled
+    set again $throttled($key)
+    unset throttled($key)
+    if {$again} {
+        afrish::throttle $key $interval
+    }
+}
+proc afrish::unthrottle {key} {
+    variable throttled
+    unset -nocomplain throttled($key)
+}
+proc afrish::popup {w x y {above {}}} {
+    update idletasks
+    set width [winfo reqwidth $w]
+    set height [winfo reqheight $w]
+    lassign [afrish::visible $w] left top area_width area_height
+    set right [expr {$left + $area_width - $width}]
+    set bottom [expr {$top + $area_height - $height}]
+    # -- with no room below, show above
+    if {$above ne "" && $y > $bottom} {
+        set y [expr {$above - $height}]
+    }
+    set x [expr {max($left, min($x, $right))}]
+    set y [expr {max($top, min($y, $bottom))}]
+    wm geometry $w +$x+$y
+    if {![winfo ismapped $w]} {
+        wm deiconify $w
+    }
+    raise $w
+}
+proc afrish::place {w how {widget {}}} {
+    update idletasks
+    set width [winfo reqwidth $w]
+    set height [winfo reqheight $w]
+    if {[winfo ismapped $w]} {
+        set width [winfo width $w]
+        set height [winfo height $w]
+    }
+    lassign [afrish::visible $w] left top area_width area_height
+    switch -- $how {
+        widget {
+            set x [expr {[winfo rootx $widget] + ([winfo width $widget] - $width) / 2}]
+            set y [expr {[winfo rooty $widget] + ([winfo height $widget] - $height) / 2}]
+        }
+        screen {
+            set x [expr {$left + ($area_width - $width) / 2}]
+            set y [expr {$top + ($area_height - $height) / 2}]
+        }
+        pointer {
+            lassign [winfo pointerxy $w] x y
+            set x [expr {$x - $width / 2}]
+            set y [expr {$y - $height / 2}]
+        }
+        default {
+            set x [winfo x $w]
+            set y [winfo y $w]
+        }
+    }
+    # -- keep the window within the visible area
+    set x [expr {max($left, min($x, $left + $area_width - $width))}]
+    set y [expr {max($top, min($y, $top + $area_height - $height))}]
+    wm geometry $w +$x+$y
+}
+proc afrish::visible {w} {
+    # -- the visible area, in root coordinates, as left top width height:
+    # -- the virtual root's, or the screen's if there is no virtual root
+    list [expr {-[winfo vrootx $w]}] [expr {-[winfo vrooty $w]}] \
+        [winfo vrootwidth $w] [winfo vrootheight $w]
+}
+proc afrish::when_mapped {w script} {
+    if {[winfo ismapped $w]} {
+        uplevel #0 $script
+    } else {
+        bind $w <Map> [list +afrish::mapped $w $script]
+    }
+}
+proc afrish::mapped {w script} {
+    afrish::unbind [list bind $w] <Map> [list afrish::mapped $w $script]
+    uplevel #0 $script
+}
+proc afrish::nofocus {w} {
+    if {[tk windowingsystem] eq "aqua"} {
+        catch {::tk::unsupported::MacWindowStyle style $w help noActivates}
+    }
+    bind $w <FocusIn> [list afrish::refocus $w]
+}
+proc afrish::refocus {w} {
+    # -- return the focus to where it was in the parent's window
+    set previous [focus -lastfor [winfo parent $w]]
+    if {$previous ne "" && [winfo toplevel $previous] ne $w} {
+        focus $previous
+    }
+}
+proc afrish::state_changed {key w window} {
+    variable states
+    # -- ignore events of the window's children, and unchanged states
+    if {$window ne $w} {
+        return
+    }
+    set state [wm state $w]
+    if {[info exists states($key)] && $states($key) eq $state} {
+        return
+    }
+    set states($key) $state
+    afrish::event clicked-$key
+}
+proc afrish::bound {key args} {
+    afrish::event [list cb1e $key {*}$args]
+}
+proc afrish::filter {key args} {
+    variable asking
+    variable decisions
+    # -- while evaluating a query, wish cannot read the decision
+    if {$asking > 0} {
+        afrish::event [list cb1p 0 $key {*}$args]
+        return
+    }
+    set id [incr afrish::requests]
+    set decisions($id) waiting
+    set timer [after $afrish::filter_timeout [list afrish::decide $id continue]]
+    afrish::event [list cb1p $id $key {*}$args]
+    vwait afrish::decisions($id)
+    after cancel $timer
+    set decision $decisions($id)
+    unset decisions($id)
+    if {$decision eq "break"} {
+        return -code break
+    }
+}
+proc afrish::decide {id decision} {
+    variable decisions
+    if {[info exists decisions($id)] && $decisions($id) eq "waiting"} {
+        set decisions($id) $decision
+    }
+}
+proc afrish::ask {id script} {
+    variable asking
+    incr asking
+    set code [catch {uplevel #0 $script} result options]
+    incr asking -1
+    if {$code == 1} {
+        set result [encoding convertto utf-8 $result]
+        set info [encoding convertto utf-8 [dict get $options -errorinfo]]
+        puts -nonewline stdout \
+            "error $id [string length $result] [string length $info]\n$result$info\n"
+        flush stdout
+    } else {
+        afrish::send "reply $id" $result
+    }
+}
+"#;
+
+// Starts a wish process, and the threads communicating with it.
+fn start_session(wish: &str, trace: bool) -> Result<TkSession, TkError> {
+    let mut wish_process = match process::Command::new(wish)
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+    {
+        Ok(wish_process) => wish_process,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            return Err(TkError::WishNotFound {
+                program: String::from(wish),
+            });
+        }
+        Err(error) => {
+            return Err(TkError::Spawn {
+                program: String::from(wish),
+                source: error,
+            });
+        }
+    };
+
+    let mut input = wish_process.stdin.take().unwrap();
+    let output = wish_process.stdout.take().unwrap();
+
+    // -- initial setup of Tcl/Tk environment
+    let close_policy = toplevel::ClosePolicy::Exit.protocol(".");
+    let setup = [
+        // frame all output to rust
+        FRAMING_PROCS,
+        // set close button to output 'exit' message, so rust can close connection
+        &close_policy,
+        "\n",
+        // remove the 'tearoff' menu option
+        "option add *tearOff 0\n",
+        // tcl function to help working with font chooser
+        "proc font_choice {w font args} {
+            set res {font }
+            append res [font actual $font]
+            afrish::event $res
+        }\n",
+        // tcl function to help working with scale widget
+        "proc scale_value {w value args} {
+            afrish::event cb1f-$w-$value
+        }\n",
+        // tell rust when widgets are destroyed, so their callbacks are freed
+        "bind all <Destroy> {+afrish::event destroyed-%W}\n",
+        // configure the communication encoding
+        "chan configure stdin -encoding utf-8\n",
+    ];
+    for script in setup {
+        if let Err(error) = input.write_all(script.as_bytes()) {
+            let _ = wish_process.kill();
+            return Err(TkError::Spawn {
+                program: String::from(wish),
+                source: error,
+            });
+        }
+    }
+
+    let (sender, receiver) = mpsc::channel::<String>();
+
+    // create thread to receive strings to send on to wish
+    thread::spawn(move || {
+        while let Ok(msg) = receiver.recv() {
+            if input.write_all(msg.as_bytes()).is_err() || input.write_all(b"\n").is_err() {
+                break;
+            }
+        }
+    });
+
+    let transport = WishProcess {
+        trace,
+        process: Mutex::new(wish_process),
+        output: Mutex::new(Some(output)),
+        sender,
+        replies: Arc::new(Mutex::new(HashMap::new())),
+        connected: Arc::new(AtomicBool::new(true)),
+    };
+
+    Ok(TkSession::new(Box::new(transport), trace))
+}
+
+// The transport for a wish process: commands are sent to a writer thread,
+// and a reader thread routes the framed messages coming back from wish
+// either to the query waiting for that reply, or to the event sink.
+struct WishProcess {
+    trace: bool,
+    process: Mutex<process::Child>,
+    output: Mutex<Option<process::ChildStdout>>,
+    sender: mpsc::Sender<String>,
+    replies: Arc<Mutex<HashMap<u64, Reply>>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl fmt::Debug for WishProcess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = self.process.lock().unwrap().id();
+        f.debug_struct("WishProcess").field("process", &id).finish()
+    }
+}
+
+impl Transport for WishProcess {
+    fn attach(&self, events: EventSink) {
+        let Some(output) = self.output.lock().unwrap().take() else {
+            return;
+        };
+        let trace = self.trace;
+        let replies = self.replies.clone();
+        let connected = self.connected.clone();
+
+        // create thread to read messages from wish, passing replies to the
+        // waiting query and events to the mainloop
+        thread::spawn(move || {
+            let mut output = std::io::BufReader::new(output);
+            while let Some(message) = read_message(&mut output, trace) {
+                match message {
+                    Message::Reply(id, value) => {
+                        if let Some(reply) = replies.lock().unwrap().remove(&id) {
+                            reply(Ok(value));
+                        }
+                    }
+                    Message::Error(id, message, error_info) => {
+                        if let Some(reply) = replies.lock().unwrap().remove(&id) {
+                            reply(Err(TkError::Tcl {
+                                message,
+                                error_info,
+                            }));
+                        }
+                    }
+                    Message::Event(value) => {
+                        if !events.event(&value) {
+                            break;
+                        }
+                    }
+                }
+            }
+            // -- wish has closed its output, so no queries can be answered
+            connected.store(false, Ordering::SeqCst);
+            replies.lock().unwrap().clear();
+            events.close();
+        });
+    }
+
+    fn tell(&self, msg: &str) {
+        let _ = self.sender.send(String::from(msg));
+    }
+
+    fn ask(&self, msg: &str, reply: Reply) -> bool {
+        let id = next_reply_id();
+        self.replies.lock().unwrap().insert(id, reply);
+        if !self.is_connected() {
+            self.replies.lock().unwrap().remove(&id);
+            return false;
+        }
+
+        self.tell(&format!("afrish::ask {} {}", id, tcl::quote(msg)));
+        true
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::SeqCst)
+    }
+
+    fn end(&self) {
+        self.connected.store(false, Ordering::SeqCst);
+        let mut process = self.process.lock().unwrap();
+        // -- wish may have already exited, which is not an error here
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+
+    fn wait(&self) -> Result<process::ExitStatus, TkError> {
+        self.process
+            .lock()
+            .unwrap()
+            .wait()
+            .map_err(|source| TkError::Wait { source })
+    }
+
+    fn exit_status(&self) -> Option<process::ExitStatus> {
+        self.process.lock().unwrap().try_wait().ok().flatten()
+    }
+}
+
+impl Drop for WishProcess {
+    fn drop(&mut self) {
+        if let Ok(process) = self.process.get_mut() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// Sends a message (tcl command) to wish and returns a future resolving to
+/// the result once wish replies, without blocking the current thread.
+/// Available with the "async" feature.
+///
+/// As with [ask_wish], an empty string is returned if the command fails.
+#[cfg(feature = "async")]
+pub fn ask_wish_async(msg: &str) -> impl Future<Output = String> + Send + 'static {
+    session().ask_wish_async(msg)
+}
+
+/// Sends a message (tcl command) to wish and returns a future resolving to
+/// the result, or an error as for [try_ask_wish]. Available with the
+/// "async" feature.
+#[cfg(feature = "async")]
+pub fn try_ask_wish_async(
+    msg: &str,
+) -> impl Future<Output = Result<String, TkError>> + Send + 'static {
+    session().try_ask_wish_async(msg)
+}
+
+/// Returns a stream of events from wish, for use in place of the
+/// [mainloop]: see [TkEventStream]. Available with the "async" feature.
+#[cfg(feature = "async")]
+pub fn event_stream() -> TkEventStream {
+    session().event_stream()
+}
+
+/// Waits up to the given time for the next event from wish, calling any
+/// callback registered for it: see [TkSession::poll_event].
+///
+/// Use this, or [update], in place of [mainloop] to run the GUI from
+/// within another loop.
+pub fn poll_event(timeout: time::Duration) -> Result<Option<WishEvent>, TkError> {
+    session().poll_event(timeout)
+}
+
+/// Handles all events already received from wish, without waiting: see
+/// [TkSession::process_pending].
+pub fn process_pending() -> Result<Option<ExitReason>, TkError> {
+    session().process_pending()
+}
+
+/// Has wish process its pending events, and then handles the resulting
+/// events: see [TkSession::update].
+pub fn update() -> Result<Option<ExitReason>, TkError> {
+    session().update()
+}
+
+/// Has wish redraw its windows, and then handles events already received:
+/// see [TkSession::update_idletasks].
+pub fn update_idletasks() -> Result<Option<ExitReason>, TkError> {
+    session().update_idletasks()
+}
+
+/// Returns a handle for running code on the mainloop from other threads:
+/// see [UiHandle].
+pub fn ui_handle() -> UiHandle {
+    session().ui_handle()
+}
+
+/// Asks wish to exit, after running any shutdown hooks, so that the
+/// [mainloop] returns: see [TkSession::quit].
+pub fn quit() {
+    session().quit();
+}
+
+/// Registers a command to call when wish shuts down: see
+/// [TkSession::on_shutdown].
+pub fn on_shutdown(command: impl FnOnce() + Send + 'static) {
+    session().on_shutdown(command);
+}
+
+/// Used to immediately end the wish process and current rust program.
+///
+/// This skips destructors and shutdown hooks: use [quit] to let the
+/// [mainloop] return instead.
+pub fn end_wish() {
+    if let Some(session) = TkSession::current() {
+        session.end();
+    }
+    process::exit(0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{counter, tcl_session, MockWish};
+    use crate::widget::TkWidget;
+
+    #[test]
+    fn read_message_1() {
+        let mut input = "reply 3 5\nhello\nevent 9\nclicked-a\n".as_bytes();
+        assert_eq!(
+            Some(Message::Reply(3, String::from("hello"))),
+            read_message(&mut input, false)
+        );
+        assert_eq!(
+            Some(Message::Event(String::from("clicked-a"))),
+            read_message(&mut input, false)
+        );
+        assert_eq!(None, read_message(&mut input, false));
+    }
+
+    #[test]
+    fn read_message_2() {
+        // -- payloads keep their newlines and multi-byte characters
+        let mut input = "reply 1 16\nline 1\nƐɔ́ 2\n\nreply 2 0\n\n".as_bytes();
+        assert_eq!(
+            Some(Message::Reply(1, String::from("line 1\nƐɔ́ 2\n"))),
+            read_message(&mut input, false)
+        );
+        assert_eq!(
+            Some(Message::Reply(2, String::new())),
+            read_message(&mut input, false)
+        );
+    }
+
+    #[test]
+    fn read_message_3() {
+        // -- unframed output is skipped, and truncated messages end input
+        let mut input = "hello world\nevent 4\nexit\nreply 1 10\nabc".as_bytes();
+        assert_eq!(
+            Some(Message::Event(String::from("exit"))),
+            read_message(&mut input, false)
+        );
+        assert_eq!(None, read_message(&mut input, false));
+    }
+
+    #[test]
+    fn read_message_4() {
+        let mut input = "error 7 7 30\nfailed!failed!\n    while executing...\n".as_bytes();
+        assert_eq!(
+            Some(Message::Error(
+                7,
+                String::from("failed!"),
+                String::from("failed!\n    while executing...")
+            )),
+            read_message(&mut input, false)
+        );
+    }
+
+    #[test]
+    fn decode_event_1() {
+        assert_eq!(
+            WishEvent::Command(String::from(".r1")),
+            decode_event("clicked-.r1")
+        );
+        assert_eq!(
+            WishEvent::Toggled(String::from(".r2"), true),
+            decode_event("cb1b-.r2-1")
+        );
+        assert_eq!(
+            WishEvent::Value(String::from(".r3"), 2.5),
+            decode_event("cb1f-.r3-2.5")
+        );
+        assert_eq!(
+            WishEvent::Other(String::from("unknown")),
+            decode_event("unknown")
+        );
+    }
+
+    #[test]
+    fn decode_event_2() {
+        match decode_event(
+            "cb1e {.r4<Button-1>#2} 10 20 110 120 5 6 0 ?? 1 {} 260 ?? .r4 5678 ?? ?? 4",
+        ) {
+            WishEvent::Bound(pattern, event) => {
+                assert_eq!(".r4<Button-1>#2", pattern);
+                assert_eq!(
+                    (10, 20, 110, 120),
+                    (event.x, event.y, event.root_x, event.root_y)
+                );
+                assert_eq!(1, event.mouse_button);
+                assert_eq!(
+                    widget::Modifiers::CONTROL | widget::Modifiers::BUTTON1,
+                    event.modifiers
+                );
+                assert_eq!(
+                    (".r4", 5678, 4),
+                    (event.widget.as_str(), event.time, event.event_type)
+                );
+                assert_eq!(
+                    ("", 0, ""),
+                    (event.unicode.as_str(), event.delta, event.data.as_str())
+                );
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        // -- characters special to the old and new formats are kept
+        match decode_event("cb1e {.r4 <Key>#3} 0 0 0 0 0 0 47 colon 0 : 1 ?? .r4 0 58 ?? 2") {
+            WishEvent::Bound(pattern, event) => {
+                assert_eq!(".r4 <Key>#3", pattern);
+                assert_eq!(
+                    ("colon", ":", 58),
+                    (
+                        event.key_symbol.as_str(),
+                        event.unicode.as_str(),
+                        event.keysym_number
+                    )
+                );
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match decode_event("cb1e .r4<Key>#3 0 0 0 0 0 0 34 braceleft 0 \\{ 1 ?? .r4 0 123 ?? 2") {
+            WishEvent::Bound(_, event) => assert_eq!("{", event.unicode),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn place_windows() {
+        // -- placing a 200x100 window on a 1920x1080 screen, within a
+        // -- virtual root twice as wide, scrolled to show its right half
+        let session = tcl_session();
+        let stubs = "\
+            array set ::winfo {reqwidth 200 reqheight 100 ismapped 0 \
+                screenwidth 1920 screenheight 1080 vrootx -1920 vrooty 0 \
+                vrootwidth 3840 vrootheight 1080 x 5000 y -50 pointerxy {1930 500}}
+            proc winfo {option w} {return $::winfo($option)}
+            proc wm {option w args} {
+                if {$option eq {geometry}} {set ::placed [lindex $args 0]}
+            }
+            proc raise {w} {}";
+        session.try_ask_wish(stubs).unwrap();
+        let place = |script: &str| {
+            session
+                .try_ask_wish(&format!("{}; set ::placed", script))
+                .unwrap()
+        };
+
+        assert_eq!("+3740+490", place("afrish::place .w screen"));
+        assert_eq!("+5000+0", place("afrish::place .w clamp"));
+        assert_eq!("+1920+450", place("afrish::place .w pointer"));
+        // -- popups are shown above if there is no room below
+        assert_eq!("+1920+900", place("afrish::popup .w 100 1050 1000"));
+        assert_eq!("+2000+300", place("afrish::popup .w 2000 300 280"));
+
+        // -- the visible area has the virtual root's size
+        place("set ::winfo(vrootwidth) 1920; set ::winfo(vrootheight) 2160");
+        assert_eq!("+2780+1030", place("afrish::place .w screen"));
+        assert_eq!("+3640+0", place("afrish::place .w clamp"));
+        assert_eq!("+1920+1050", place("afrish::popup .w 100 1050 1000"));
+    }
+
+    #[test]
+    fn ask_after_close() {
+        // -- queries return an empty string, or an error, once wish finishes
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let entry = crate::make_entry(&session.root());
+        mock.close();
+
+        assert_eq!("", entry.value_get());
+        assert!(matches!(
+            session.try_ask_wish("winfo exists ."),
+            Err(TkError::Disconnected)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "uses a widget from another session")]
+    fn widget_from_other_session() {
+        use crate::widget::TkLabelOptions;
+
+        let mock = MockWish::new();
+        let session = mock.session();
+        let other = MockWish::new().session();
+        let button = {
+            let _guard = session.enter();
+            crate::make_button(&session.root())
+        };
+
+        let _guard = other.enter();
+        crate::make_label(&other.root()).text("x");
+        button.text("x");
+    }
+
+    #[test]
+    fn nested_mainloop() {
+        // -- a mainloop started from a callback fails, rather than deadlocking
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let button = crate::make_button(&session.root());
+        let result = Arc::new(Mutex::new(None));
+        let result_clone = result.clone();
+        button.command(move || {
+            *result_clone.lock().unwrap() = Some(mainloop());
+        });
+
+        mock.event(&format!("clicked-{}", button.id));
+        mock.close();
+        assert!(matches!(session.mainloop(), Ok(ExitReason::WishDied(_))));
+        assert!(matches!(
+            *result.lock().unwrap(),
+            Some(Err(TkError::LoopRunning))
+        ));
+        assert_eq!(None, *session.inner.loop_thread.lock().unwrap());
+    }
+
+    #[test]
+    fn loop_thread() {
+        // -- the thread reading events is only recorded while it reads them
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let button = crate::make_button(&session.root());
+        let inner = session.inner.clone();
+        let during = Arc::new(Mutex::new(None));
+        let during_clone = during.clone();
+        button.command(move || {
+            *during_clone.lock().unwrap() = *inner.loop_thread.lock().unwrap();
+        });
+
+        mock.event(&format!("clicked-{}", button.id));
+        session
+            .poll_event(time::Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(Some(thread::current().id()), *during.lock().unwrap());
+        assert_eq!(None, *session.inner.loop_thread.lock().unwrap());
+
+        *during.lock().unwrap() = None;
+        mock.event(&format!("clicked-{}", button.id));
+        session.process_pending().unwrap();
+        assert_eq!(Some(thread::current().id()), *during.lock().unwrap());
+        assert_eq!(None, *session.inner.loop_thread.lock().unwrap());
+    }
+
+    #[test]
+    fn callbacks_1() {
+        // -- unregistered callbacks are no longer called
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let button = crate::make_button(&session.root());
+        let (count, command) = counter();
+        let handle = button.command(command);
+        assert!(handle.is_registered());
+
+        mock.event(&format!("clicked-{}", button.id));
+        session.process_pending().unwrap();
+        handle.unregister();
+        assert!(!handle.is_registered());
+        mock.event(&format!("clicked-{}", button.id));
+        session.process_pending().unwrap();
+        assert_eq!(1, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn callbacks_2() {
+        // -- destroying a widget removes its callbacks and its children's
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let frame = crate::make_frame(&session.root());
+        let button = crate::make_button(&frame);
+        let other = crate::make_button(&session.root());
+        let (count, command) = counter();
+        let handle = button.command(command);
+        let other_handle = other.command(|| {});
+        let bind_handle = frame.bind("<Button-1>", |_| {});
+
+        frame.destroy();
+        assert!(mock.commands().contains(&format!("destroy {}", frame.id)));
+        session.process_pending().unwrap();
+        assert!(!handle.is_registered());
+        assert!(!bind_handle.is_bound());
+        assert!(other_handle.is_registered());
+        mock.event(&format!("clicked-{}", button.id));
+        session.process_pending().unwrap();
+        assert_eq!(0, count.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn callbacks_3() {
+        // -- widgets destroyed within Tcl also remove their callbacks
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let button = crate::make_button(&session.root());
+        let handle = button.command(|| {});
+
+        mock.event(&format!("destroyed-{}", button.id));
+        session.process_pending().unwrap();
+        assert!(!handle.is_registered());
+    }
+
+    #[test]
+    fn callbacks_4() {
+        // -- timers are called once only
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let (count, command) = counter();
+        let handle = crate::after(10, command);
+        let name = mock
+            .last_command()
+            .unwrap()
+            .split("clicked-")
+            .nth(1)
+            .unwrap()
+            .trim_end_matches([' ', '}'])
+            .to_string();
+
+        mock.event(&format!("clicked-{}", name));
+        mock.event(&format!("clicked-{}", name));
+        session.process_pending().unwrap();
+        assert_eq!(1, count.load(Ordering::SeqCst));
+        assert!(!handle.is_pending());
+    }
+
+    #[test]
+    fn bindings() {
+        // -- several commands can be bound to a pattern, and unbound separately
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let frame = crate::make_frame(&session.root());
+        let (first_count, first) = counter();
+        let (second_count, second) = counter();
+        let first = frame.bind("<KeyPress>", move |_| first());
+        let second = frame.bind("<KeyPress>", move |_| second());
+        assert_ne!(first.key(), second.key());
+        assert_eq!(
+            Some(format!(
+                "bind {{{}}} {{<KeyPress>}} {{+afrish::bound {{{}}} %x %y %X %Y %h %w %k %K %b %A %s %D %W %t %N %d %T}}",
+                frame.id,
+                second.key()
+            )),
+            mock.last_command()
+        );
+
+        mock.event(&format!("cb1e {{{}}} 0 0 0 0 0 0 0 a 0", first.key()));
+        mock.event(&format!("cb1e {{{}}} 0 0 0 0 0 0 0 a 0", second.key()));
+        session.process_pending().unwrap();
+        first.unbind();
+        assert!(!first.is_bound());
+        assert_eq!(
+            Some(format!(
+                "afrish::unbind {{bind {{{}}}}} {{<KeyPress>}} {{afrish::bound {{{}}} %x %y %X %Y %h %w %k %K %b %A %s %D %W %t %N %d %T}}",
+                frame.id,
+                first.key()
+            )),
+            mock.last_command()
+        );
+        mock.event(&format!("cb1e {{{}}} 0 0 0 0 0 0 0 a 0", first.key()));
+        mock.event(&format!("cb1e {{{}}} 0 0 0 0 0 0 0 a 0", second.key()));
+        session.process_pending().unwrap();
+        assert_eq!(
+            (1, 2),
+            (
+                first_count.load(Ordering::SeqCst),
+                second_count.load(Ordering::SeqCst)
+            )
+        );
+    }
+
+    #[test]
+    fn filters() {
+        // -- the filter's result is sent back to the waiting binding
+        let mock = MockWish::new();
+        let session = mock.session();
+        let _guard = session.enter();
+        let entry = crate::make_entry(&session.root());
+        let filter = entry.bind_filter("<KeyPress>", |event| {
+            if event.key_symbol == "a" {
+                widget::Propagation::Break
+            } else {
+                widget::Propagation::Continue
+            }
+        });
+        assert_eq!(
+            Some(format!(
+                "bind {{{}}} {{<KeyPress>}} {{+afrish::filter {{{}}} %x %y %X %Y %h %w %k %K %b %A %s %D %W %t %N %d %T}}",
+                entry.id,
+                filter.key()
+            )),
+            mock.last_command()
+        );
+
+        mock.clear();
+        mock.event(&format!("cb1p 4 {{{}}} 0 0 0 0 0 0 38 a 0", filter.key()));
+        mock.event(&format!("cb1p 5 {{{}}} 0 0 0 0 0 0 56 b 0", filter.key()));
+        // -- wish is not waiting for events sent while it evaluates a query
+        mock.event(&format!("cb1p 0 {{{}}} 0 0 0 0 0 0 38 a 0", filter.key()));
+        session.process_pending().unwrap();
+        assert_eq!(
+            vec![
+                String::from("afrish::decide 4 break"),
+                String::from("afrish::decide 5 continue")
+            ],
+            mock.commands()
+        );
+
+        // -- once unbound, wish is told to continue
+        filter.unbind();
+        mock.clear();
+        mock.event(&format!("cb1p 6 {{{}}} 0 0 0 0 0 0 38 a 0", filter.key()));
+        session.process_pending().unwrap();
+        assert_eq!(
+            vec![String::from("afrish::decide 6 continue")],
+            mock.commands()
+        );
+    }
+
+    #[test]
+    fn decode_event_3() {
+        assert_eq!(
+            WishEvent::Filtered(
+                String::from(".r5<KeyPress>#1"),
+                widget::TkEvent {
+                    x: 1,
+                    y: 2,
+                    root_x: 3,
+                    root_y: 4,
+                    key_code: 38,
+                    key_symbol: String::from("a"),
+                    unicode: String::from("a"),
+                    widget: String::from(".r5"),
+                    time: 99,
+                    keysym_number: 97,
+                    event_type: 2,
+                    ..Default::default()
+                },
+                7
+            ),
+            decode_event("cb1p 7 .r5<KeyPress>#1 1 2 3 4 ?? ?? 38 a ?? a 0 ?? .r5 99 97 ?? 2")
+        );
+    }
+
+    #[test]
+    fn tk_error_display() {
+        let error = TkError::Tcl {
+            message: String::from("unknown color name \"bleu\""),
+            error_info: String::new(),
+        };
+        assert_eq!("Tcl error: unknown color name \"bleu\"", error.to_string());
+
+        let error = TkError::WishNotFound {
+            program: String::from("wish8.7"),
+        };
+        assert_eq!("could not find the wish8.7 program", error.to_string());
     }
 }